@@ -1,7 +1,7 @@
 use anyhow::{format_err, Ok, Result};
 use build_mod::CodeGeneration;
 use ethabi::{Contract, Error, ParamType};
-use heck::ToSnakeCase;
+use heck::{ToSnakeCase, ToUpperCamelCase};
 use serde::Deserialize;
 use serde_json::from_reader;
 use std::{
@@ -34,12 +34,22 @@ ORDER BY (evt_block_time,
 \tevt_index);
 ";
 
+#[derive(Deserialize)]
+struct TokenAmountConfig {
+    event: String,
+    param: String,
+    /// `contract` for the emitting contract or the name of an address parameter
+    token: String,
+}
+
 #[derive(Deserialize)]
 struct SubstreamContract {
     name: String,
     abi_file: String,
     table_prefix: Option<String>,
     address: Option<String>,
+    #[serde(default)]
+    token_amounts: Vec<TokenAmountConfig>,
 }
 
 #[derive(Deserialize)]
@@ -95,10 +105,56 @@ fn write_param_type_sql(param_type: &ParamType, writer: &mut impl Write) -> Resu
     Ok(())
 }
 
+/// Parameter names of the config match the ones of the ABI once both are in snake case, which is
+/// how they are named in the generated code and tables.
+fn same_param_name(config_name: &str, abi_name: &str) -> bool {
+    config_name.to_snake_case() == abi_name.to_snake_case()
+}
+
+fn find_token_amount<'a>(
+    token_amounts: &'a [TokenAmountConfig],
+    event_name: &str,
+    param_name: &str,
+) -> Option<&'a TokenAmountConfig> {
+    token_amounts
+        .iter()
+        .find(|amount| amount.event == event_name && same_param_name(&amount.param, param_name))
+}
+
+fn validate_token_amounts(contract: &Contract, token_amounts: &[TokenAmountConfig]) -> Result<()> {
+    for amount in token_amounts {
+        let event = contract
+            .events_by_name(&amount.event)
+            .map_err(|_| format_err!("Unknown event `{}` in token_amounts", amount.event))?
+            .first()
+            .ok_or_else(|| format_err!("Unknown event `{}` in token_amounts", amount.event))?;
+        let param = event
+            .inputs
+            .iter()
+            .find(|param| same_param_name(&amount.param, &param.name))
+            .ok_or_else(|| format_err!("Unknown parameter `{}` for event `{}`", amount.param, amount.event))?;
+        if !matches!(param.kind, ParamType::Uint(_) | ParamType::Int(_)) {
+            return Err(format_err!("Token amount `{}.{}` is not an integer", amount.event, amount.param));
+        }
+        if amount.token != "contract" {
+            let token = event
+                .inputs
+                .iter()
+                .find(|param| same_param_name(&amount.token, &param.name))
+                .ok_or_else(|| format_err!("Unknown token parameter `{}` for event `{}`", amount.token, amount.event))?;
+            if token.kind != ParamType::Address {
+                return Err(format_err!("Token `{}.{}` is not an address", amount.event, amount.token));
+            }
+        }
+    }
+    Ok(())
+}
+
 fn write_contract_sql(
     contract: Contract,
     database_name: &str,
     table_prefix: &Option<String>,
+    token_amounts: &[TokenAmountConfig],
     writer: &mut impl Write,
 ) -> Result<()> {
     for (_, events) in contract.events {
@@ -117,6 +173,9 @@ fn write_contract_sql(
                 write!(writer, "\t`{}` ", param.name.to_snake_case())?;
                 write_param_type_sql(&param.kind, writer)?;
                 write!(writer, ",\n")?;
+                if find_token_amount(token_amounts, &event.name, &param.name).is_some() {
+                    write!(writer, "\t`{}_normalized` Nullable(Decimal256(18)),\n", param.name.to_snake_case())?;
+                }
             }
             writer.write(SQL_TABLE_END)?;
         }
@@ -145,12 +204,7 @@ ORDER BY id;
     Ok(())
 }
 
-pub fn write_abi_sql<S: AsRef<str>>(
-    path: S,
-    database_name: &str,
-    table_prefix: &Option<String>,
-    writer: &mut impl Write,
-) -> Result<(), anyhow::Error> {
+fn load_contract<S: AsRef<str>>(path: S) -> Result<Contract, anyhow::Error> {
     let normalized_path = normalize_path(path.as_ref())?;
     let source_file = fs::File::open(&normalized_path).map_err(|_| {
         Error::Other(Cow::Owned(format!(
@@ -158,8 +212,18 @@ pub fn write_abi_sql<S: AsRef<str>>(
             normalized_path.display()
         )))
     })?;
-    let contract = Contract::load(source_file)?;
-    write_contract_sql(contract, database_name, table_prefix, writer)?;
+    Ok(Contract::load(source_file)?)
+}
+
+fn write_abi_sql<S: AsRef<str>>(
+    path: S,
+    database_name: &str,
+    table_prefix: &Option<String>,
+    token_amounts: &[TokenAmountConfig],
+    writer: &mut impl Write,
+) -> Result<(), anyhow::Error> {
+    let contract = load_contract(path)?;
+    write_contract_sql(contract, database_name, table_prefix, token_amounts, writer)?;
     Ok(())
 }
 
@@ -195,11 +259,33 @@ fn generate_code(config: &SubstreamConfig) -> Result<()> {
 fn generate_for(contract: &SubstreamContract) -> Result<()> {
     let abigen = Abigen::new(&contract.name, contract.address.clone(), &contract.abi_file)?;
 
+    validate_token_amounts(&load_contract(&contract.abi_file)?, &contract.token_amounts)?;
+
     let mut event_extension = EventExtension::new();
     event_extension.extend_event_derive("to_table_derive::ToTableChange");
     if let Some(prefix) = &contract.table_prefix {
         event_extension.extend_event_attribute(format!("table_prefix=\"{}\"", prefix).as_str());
     }
+    if !contract.token_amounts.is_empty() {
+        let token_amounts = contract
+            .token_amounts
+            .iter()
+            .map(|amount| {
+                let token = match amount.token.as_str() {
+                    "contract" => "contract".to_string(),
+                    token => token.to_snake_case(),
+                };
+                format!(
+                    "{}:{}:{}",
+                    amount.event.to_upper_camel_case(),
+                    amount.param.to_snake_case(),
+                    token
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        event_extension.extend_event_attribute(format!("token_amounts=\"{}\"", token_amounts).as_str());
+    }
     let extension = AbiExtension::new(event_extension);
     abigen
         .add_extension(extension)
@@ -218,6 +304,7 @@ fn generate_sql_schema(config: &SubstreamConfig) -> Result<()> {
             &contract.abi_file,
             &config.database,
             &contract.table_prefix,
+            &contract.token_amounts,
             &mut writer,
         )?;
     }
@@ -1,5 +1,7 @@
 use std::collections::HashMap;
 
+use substreams::store::{StoreGet, StoreGetInt64};
+use substreams::Hex;
 use substreams_database_change::pb::database::{table_change::Operation, DatabaseChanges};
 
 use crate::{
@@ -13,6 +15,7 @@ pub fn push_create(
     keys: HashMap<String, String>,
     ordinal: u64,
     value: EventWithInfo<Events>,
+    token_decimals: &StoreGetInt64,
 )
 {
     let table_change = changes.push_change_composite(
@@ -37,4 +40,15 @@ pub fn push_create(
 
     // event specific
     value.event.add_table_changes(table_change);
+
+    // token amounts divided by 10^decimals of their token
+    for amount in value.event.get_token_amounts() {
+        let token_address = amount.token_address(&value.info.contract_address);
+        if let Some(decimals) = token_decimals.get_last(Hex(token_address).to_string()) {
+            table_change.change(
+                format!("{}_normalized", amount.column),
+                (None, amount.normalized(decimals)),
+            );
+        }
+    }
 }
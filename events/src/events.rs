@@ -8,6 +8,39 @@ pub trait ToTableChange {
     fn get_table_name(&self) -> &'static str;
 
     fn get_contract_name(&self) -> &'static str;
+
+    fn get_token_amounts(&self) -> Vec<TokenAmount> {
+        vec![]
+    }
+}
+
+/// Token whose decimals are used to normalize a token amount.
+pub enum TokenSource {
+    /// The contract which emitted the event.
+    Contract,
+    /// An address parameter of the event.
+    Address(Vec<u8>),
+}
+
+/// Event parameter configured as a token amount in `substream_config.json`.
+pub struct TokenAmount {
+    pub column: &'static str,
+    pub token: TokenSource,
+    pub amount: substreams::scalar::BigInt,
+}
+
+impl TokenAmount {
+    pub fn token_address<'a>(&'a self, contract_address: &'a Vec<u8>) -> &'a Vec<u8> {
+        match &self.token {
+            TokenSource::Contract => contract_address,
+            TokenSource::Address(address) => address,
+        }
+    }
+
+    /// Amount divided by `10^decimals` of its token.
+    pub fn normalized(&self, decimals: i64) -> String {
+        self.amount.to_decimal(decimals as u64).to_string()
+    }
 }
 
 pub trait TableField {
@@ -78,6 +111,40 @@ impl<T1:TableField,T2:TableField,T3:TableField,T4:TableField,T5:TableField> Tabl
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::{TokenAmount, TokenSource};
+    use substreams::scalar::{BigDecimal, BigInt};
+
+    #[test]
+    fn test_token_address() {
+        let contract = vec![1u8; 20];
+        let token = vec![2u8; 20];
+        let from_contract = TokenAmount {
+            column: "value",
+            token: TokenSource::Contract,
+            amount: BigInt::from(1),
+        };
+        let from_param = TokenAmount {
+            column: "amount_in",
+            token: TokenSource::Address(token.clone()),
+            amount: BigInt::from(1),
+        };
+
+        assert_eq!(from_contract.token_address(&contract), &contract);
+        assert_eq!(from_param.token_address(&contract), &token);
+    }
 
-
-
+    #[test]
+    fn test_normalized() {
+        let amount = TokenAmount {
+            column: "value",
+            token: TokenSource::Contract,
+            amount: BigInt::from(1_500_000),
+        };
+
+        let decimal = |value: &str| BigDecimal::try_from(value).unwrap();
+        assert_eq!(decimal(&amount.normalized(6)), decimal("1.5"));
+        assert_eq!(decimal(&amount.normalized(0)), decimal("1500000"));
+    }
+}
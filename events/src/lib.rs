@@ -1,15 +1,20 @@
 mod abi;
 mod db;
 mod events;
+mod rpc;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use abi::Events;
 use events::ToTableChange;
 use prost_types::Timestamp;
 use substreams_database_change::pb::database::DatabaseChanges;
 
-use substreams::Hex;
+use substreams::pb::substreams::store_delta::Operation;
+use substreams::store::{
+    DeltaExt, DeltaInt64, Deltas, StoreAdd, StoreAddInt64, StoreGetInt64, StoreNew, StoreSet, StoreSetInt64,
+};
+use substreams::{log, Hex};
 use substreams_ethereum::pb::eth;
 
 use crate::db::push_create;
@@ -59,16 +64,60 @@ fn get_events(
     Ok(events)
 }
 
+/// Counts the blocks in which a token of a token amount is seen.
+#[substreams::handlers::store]
+fn store_token_addresses(block: eth::v2::Block, store: StoreAddInt64) {
+    let events = match get_events(&block) {
+        Ok(events) => events,
+        Err(err) => {
+            log::info!("skipping token addresses of block {}: {}", block.number, err);
+            return;
+        }
+    };
+
+    let mut token_addresses = HashSet::new();
+    for evt in events {
+        for amount in evt.event.get_token_amounts() {
+            token_addresses.insert(Hex(amount.token_address(&evt.info.contract_address)).to_string());
+        }
+    }
+    for token_address in token_addresses {
+        store.add(0, token_address, 1);
+    }
+}
+
+#[substreams::handlers::store]
+fn store_token_decimals(token_address_deltas: Deltas<DeltaInt64>, store: StoreSetInt64) {
+    // a failed call is not stored, so it is retried the next times the token is seen
+    for delta in token_address_deltas
+        .deltas
+        .iter()
+        .operation_not_eq(Operation::Delete)
+        .filter(|delta| rpc::should_fetch_decimals(delta.new_value))
+    {
+        let token_address = hex::decode(&delta.key).unwrap();
+        if let Some(decimals) = rpc::token_decimals_call(&token_address) {
+            store.set(delta.ordinal, &delta.key, &(decimals as i64));
+        }
+    }
+}
+
 #[substreams::handlers::map]
-fn db_out(block: eth::v2::Block) -> Result<DatabaseChanges, substreams::errors::Error> {
-  transform(block)
+fn db_out(
+    block: eth::v2::Block,
+    token_decimals: StoreGetInt64, /* store_token_decimals */
+) -> Result<DatabaseChanges, substreams::errors::Error> {
+  transform(block, &token_decimals)
 }
 
-pub fn transform(block: eth::v2::Block) -> Result<DatabaseChanges, substreams::errors::Error> {
+pub fn transform(
+    block: eth::v2::Block,
+    token_decimals: &StoreGetInt64,
+) -> Result<DatabaseChanges, substreams::errors::Error> {
     let mut database_changes: DatabaseChanges = Default::default();
     let events = get_events(&block)?;
 
-    transform_events_to_database_changes(&mut database_changes, events);
+    transform_events_to_database_changes(&mut database_changes, events, token_decimals);
 
     Ok(database_changes)
 }
@@ -78,6 +127,7 @@ pub fn transform(block: eth::v2::Block) -> Result<DatabaseChanges, substreams::e
 fn transform_events_to_database_changes(
     changes: &mut DatabaseChanges,
     events: Vec<EventWithInfo<Events>>,
+    token_decimals: &StoreGetInt64,
 ) {
     for evt in events {
         let table_name = evt.event.get_table_name();
@@ -95,6 +145,6 @@ fn transform_events_to_database_changes(
             "evt_block_time".to_string(),
             evt.info.evt_block_time.to_string(),
         );
-        push_create(changes, table_name, keys, 0, evt);
+        push_create(changes, table_name, keys, 0, evt, token_decimals);
     }
 }
@@ -0,0 +1,50 @@
+use substreams::{log, scalar::BigInt, Hex};
+use substreams_ethereum::{
+    pb::eth::rpc::{RpcCall, RpcCalls},
+    rpc::eth_call,
+};
+
+// decimals()
+const DECIMALS_SELECTOR: [u8; 4] = [0x31, 0x3c, 0xe5, 0x67];
+
+/// Decimals are fetched the first time a token is seen and again once it has been seen in 2, 4, 8...
+/// blocks, so a failed call is retried without a call for every block of the token.
+pub fn should_fetch_decimals(blocks_seen: i64) -> bool {
+    blocks_seen > 0 && blocks_seen & (blocks_seen - 1) == 0
+}
+
+pub fn token_decimals_call(token_address: &Vec<u8>) -> Option<u64> {
+    let responses = eth_call(&RpcCalls {
+        calls: vec![RpcCall {
+            to_addr: token_address.clone(),
+            data: DECIMALS_SELECTOR.to_vec(),
+        }],
+    })
+    .responses;
+
+    let response = responses.first()?;
+    if response.failed || response.raw.len() < 32 {
+        log::debug!("{} decimals `eth_call` failed", Hex(token_address));
+        return None;
+    }
+
+    let decimals = BigInt::from_unsigned_bytes_be(&response.raw[0..32]);
+    // if the number of decimals are bigger than 255, the contract is not an ERC20 token
+    if decimals >= BigInt::from(255) {
+        log::info!("ignoring token address {} because the decimals are bigger than 255", Hex(token_address));
+        return None;
+    }
+
+    Some(decimals.to_u64())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::should_fetch_decimals;
+
+    #[test]
+    fn test_should_fetch_decimals() {
+        let fetched: Vec<i64> = (0..20).filter(|count| should_fetch_decimals(*count)).collect();
+        assert_eq!(fetched, vec![1, 2, 4, 8, 16]);
+    }
+}
@@ -0,0 +1,427 @@
+use crate::protocol::{Profile, Protocol};
+use tiny_keccak::{Hasher, Keccak};
+
+/// Params understood by `Config::parse`, the other params of a module are left to its own parser.
+pub const KEYS: [&str; 14] = [
+    "chain",
+    "protocols",
+    "factory",
+    "position_manager",
+    "pool_manager",
+    "v2_factory",
+    "error_pool",
+    "weth",
+    "usd_token",
+    "usd_pool",
+    "stable_coins",
+    "whitelist_tokens",
+    "minimum_eth_locked",
+    "bootstrap_block",
+];
+
+const ETHEREUM_STABLE_COINS: [&str; 6] = [
+    "6b175474e89094c44da98b954eedeac495271d0f", // DAI
+    "a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48", // USDC
+    "dac17f958d2ee523a2206206994597c13d831ec7", // USDT
+    "0000000000085d4780b73119b644ae5ecd22b376", // TUSD
+    "956f47f50a910163d8bf957cf5846d573e7f87ca", // FEI
+    "4dd28568d05f09b02220b09c2cb307bfd837cb95", // PRINTS
+];
+
+const ETHEREUM_WHITELIST_TOKENS: [&str; 21] = [
+    "c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2", // WETH
+    "6b175474e89094c44da98b954eedeac495271d0f", // DAI
+    "a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48", // USDC
+    "dac17f958d2ee523a2206206994597c13d831ec7", // USDT
+    "0000000000085d4780b73119b644ae5ecd22b376", // TUSD
+    "2260fac5e5542a773aa44fbcfedf7c193bc2c599", // WBTC
+    "5d3a536e4d6dbd6114cc1ead35777bab948e3643", // cDAI
+    "39aa39c021dfbae8fac545936693ac917d5e7563", // cUSDC
+    "86fadb80d8d2cff3c3680819e4da99c10232ba0f", // EBASE
+    "57ab1ec28d129707052df4df418d58a2d46d5f51", // sUSD
+    "9f8f72aa9304c8b593d555f12ef6589cc3a579a2", // MKR
+    "c00e94cb662c3520282e6f5717214004a7f26888", // COMP
+    "514910771af9ca656af840dff83e8264ecf986ca", // LINK
+    "c011a73ee8576fb46f5e1c5751ca3b9fe0af2a6f", // SNX
+    "0bc529c00c6401aef6d220be8c6ea1667f6ad93e", // YFI
+    "111111111117dc0aa78b770fa6a738034120c302", // 1INCH
+    "df5e0e81dff6faf3a7e52ba697820c5e32d806a8", // yCurv
+    "956f47f50a910163d8bf957cf5846d573e7f87ca", // FEI
+    "7d1afa7b718fb893db30a3abc0cfc608aacfebb0", // MATIC
+    "7fc66500c84a76ad7e9c93437bfc5ac33e2ddae9", // AAVE
+    "fe2e637202056d30016725477c5da089ab0a043a", // sETH2
+];
+
+const ARBITRUM_ONE_STABLE_COINS: [&str; 4] = [
+    "ff970a61a04b1ca14834a43f5de4533ebddb5cc8", // USDC.e
+    "af88d065e77c8cc2239327c5edb3a432268e5831", // USDC
+    "da10009cbd5d07dd0cecc66161fc93d7c9000da1", // DAI
+    "fd086bc7cd5c481dcc9c85ebe478a1c0b69fcbb9", // USDT
+];
+
+const ARBITRUM_ONE_WHITELIST_TOKENS: [&str; 7] = [
+    "82af49447d8a07e3bd95bd0d56f35241523fbab1", // WETH
+    "ff970a61a04b1ca14834a43f5de4533ebddb5cc8", // USDC.e
+    "af88d065e77c8cc2239327c5edb3a432268e5831", // USDC
+    "da10009cbd5d07dd0cecc66161fc93d7c9000da1", // DAI
+    "fd086bc7cd5c481dcc9c85ebe478a1c0b69fcbb9", // USDT
+    "2f2a2543b76a4166549f7aab2e75bef0aefc5b0f", // WBTC
+    "912ce59144191c1204e64559fe8253a0e49e6548", // ARB
+];
+
+/// Factory and position manager of the Uniswap v3 forks deployed on a chain.
+fn fork_deployments(chain: &str) -> Vec<Profile> {
+    let profile = |protocol, factory, position_manager| Profile {
+        protocol,
+        factory: address(factory),
+        position_manager: address(position_manager),
+    };
+    let pancakeswap_v3 = profile(
+        Protocol::PancakeswapV3,
+        "0bfbcf9fa4f9c56b0f40a671ad40e0805a091865",
+        "46a15b0b27311cedf172ab29e4f4766fbe7f4364",
+    );
+    match chain {
+        "ethereum" | "mainnet" => vec![
+            profile(
+                Protocol::SushiswapV3,
+                "baceb8ec6b9355dfc0269c18bac9d6e2bdc29c4f",
+                "2214a42d8e2a1d20635c2cb0664422c528b6a432",
+            ),
+            pancakeswap_v3,
+        ],
+        "arbitrum-one" | "arbitrum" => vec![
+            profile(
+                Protocol::SushiswapV3,
+                "1af415a1eba07a4986a52b6f2e7de7003d82231e",
+                "f0cbce1942a68beb3d1b73f0dd86c8dcc363ef49",
+            ),
+            pancakeswap_v3,
+        ],
+        _ => vec![],
+    }
+}
+
+/// Uniswap v4 `PoolManager`, the singleton holding every v4 pool of a chain.
+fn v4_deployment(chain: &str) -> Option<Vec<u8>> {
+    match chain {
+        "ethereum" | "mainnet" => Some(address("000000000004444c5dc75cb358380d2e3de08a90")),
+        "arbitrum-one" | "arbitrum" => Some(address("360e68faccca8ca495c1b759fd9eee466db9fb32")),
+        _ => None,
+    }
+}
+
+/// Chain specific contracts, tokens and thresholds of the Uniswap v3 modules, given as `key=value`
+/// module params separated by `&`, e.g. `chain=arbitrum-one&minimum_eth_locked=10`.
+///
+/// `chain` picks one of the presets, `ethereum` by default, the other params override single
+/// values of it. Addresses are hex, with or without `0x`, lists are separated by `,`.
+///
+/// `protocols` lists the v3-style DEXes indexed side by side, `uniswap_v3` by default, e.g.
+/// `protocols=uniswap_v3,sushiswap_v3,pancakeswap_v3`. `factory` and `position_manager` are the
+/// Uniswap v3 ones, the forks use their deployments on the chain. `uniswap_v4` adds the pools of
+/// the v4 `PoolManager` of the chain, or of the one given with `pool_manager`.
+#[derive(Debug, PartialEq)]
+pub struct Config {
+    pub factory: Vec<u8>,
+    pub position_manager: Vec<u8>,
+    /// Indexed protocols, the Uniswap v3 one built from `factory` and `position_manager`.
+    pub profiles: Vec<Profile>,
+    /// Uniswap v4 `PoolManager`, empty unless v4 pools are indexed.
+    pub pool_manager: Vec<u8>,
+    pub v2_factory: Vec<u8>,
+    /// Pool created by the factory which is never indexed, empty for none.
+    pub error_pool: Vec<u8>,
+    pub weth: String,
+    /// Stablecoin of `usd_pool`, its price against WETH is the USD price of ETH.
+    pub usd_token: String,
+    pub usd_pool: String,
+    /// Tokens worth one dollar while `store_stablecoin_pegs` does not report them depegged.
+    pub stable_coins: Vec<String>,
+    pub whitelist_tokens: Vec<String>,
+    /// ETH locked in a pool for its price to be used, unless the other token is whitelisted.
+    pub minimum_eth_locked: u64,
+    /// Block `graph_out` creates the `Factory` and `Bundle` entities at.
+    pub bootstrap_block: u64,
+}
+
+impl Config {
+    pub fn preset(chain: &str) -> Option<Config> {
+        match chain {
+            "ethereum" | "mainnet" => Some(Config {
+                factory: address("1f98431c8ad98523631ae4a59f267346ea31f984"),
+                position_manager: address("c36442b4a4522e871399cd717abdd847ab11fe88"),
+                profiles: vec![],
+                pool_manager: vec![],
+                v2_factory: address("5c69bee701ef814a2b6a3edd4b1652cb9cc5aa6f"),
+                error_pool: address("8fe8d9bb8eeba3ed688069c3d6b556c9ca258248"),
+                weth: "c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2".to_string(),
+                usd_token: "a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48".to_string(),
+                usd_pool: "8ad599c3a0ff1de082011efddc58f1908eb6e6d8".to_string(),
+                stable_coins: ETHEREUM_STABLE_COINS.iter().map(|t| t.to_string()).collect(),
+                whitelist_tokens: ETHEREUM_WHITELIST_TOKENS.iter().map(|t| t.to_string()).collect(),
+                minimum_eth_locked: 52,
+                bootstrap_block: 12369621,
+            }),
+            "arbitrum-one" | "arbitrum" => Some(Config {
+                factory: address("1f98431c8ad98523631ae4a59f267346ea31f984"),
+                position_manager: address("c36442b4a4522e871399cd717abdd847ab11fe88"),
+                profiles: vec![],
+                pool_manager: vec![],
+                v2_factory: address("f1d7cc64fb4452f05c498126312ebe29f30fbcf9"),
+                error_pool: vec![],
+                weth: "82af49447d8a07e3bd95bd0d56f35241523fbab1".to_string(),
+                usd_token: "ff970a61a04b1ca14834a43f5de4533ebddb5cc8".to_string(),
+                usd_pool: "17c14d2c404d167802b16c450d3c99f88f2c4f4d".to_string(),
+                stable_coins: ARBITRUM_ONE_STABLE_COINS.iter().map(|t| t.to_string()).collect(),
+                whitelist_tokens: ARBITRUM_ONE_WHITELIST_TOKENS.iter().map(|t| t.to_string()).collect(),
+                minimum_eth_locked: 20,
+                bootstrap_block: 163,
+            }),
+            _ => None,
+        }
+        .map(|mut config| {
+            config.profiles = vec![config.uniswap_v3_profile()];
+            config
+        })
+    }
+
+    pub fn parse(params: &str) -> Result<Config, substreams::errors::Error> {
+        Self::parse_with(params, &[])
+    }
+
+    /// Same as `parse`, skipping the `module_keys` params read by the module itself.
+    pub fn parse_with(params: &str, module_keys: &[&str]) -> Result<Config, substreams::errors::Error> {
+        let pairs = params
+            .split('&')
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .map(|pair| {
+                pair.split_once('=')
+                    .map(|(key, value)| (key.trim(), value.trim()))
+                    .ok_or_else(|| anyhow::anyhow!("invalid param `{}`, expected key=value", pair))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let chain = pairs
+            .iter()
+            .find(|(key, _)| *key == "chain")
+            .map_or("ethereum", |(_, value)| *value);
+        let mut result = Config::preset(chain).ok_or_else(|| anyhow::anyhow!("unknown chain `{}`", chain))?;
+        let mut protocols = vec![Protocol::UniswapV3];
+        let mut uniswap_v4 = false;
+
+        for (key, value) in pairs {
+            let invalid = || anyhow::anyhow!("invalid value `{}` for param `{}`", value, key);
+            match key {
+                "chain" => {}
+                "protocols" => {
+                    let names: Vec<&str> = value.split(',').map(str::trim).filter(|p| !p.is_empty()).collect();
+                    uniswap_v4 = names.contains(&"uniswap_v4");
+                    protocols = names
+                        .into_iter()
+                        .filter(|name| *name != "uniswap_v4")
+                        .map(Protocol::from_name)
+                        .collect::<Option<Vec<_>>>()
+                        .ok_or_else(invalid)?
+                }
+                "factory" => result.factory = parse_address(value).map(|a| address(&a)).ok_or_else(invalid)?,
+                "position_manager" => {
+                    result.position_manager = parse_address(value).map(|a| address(&a)).ok_or_else(invalid)?
+                }
+                "pool_manager" => {
+                    result.pool_manager = parse_address(value).map(|a| address(&a)).ok_or_else(invalid)?
+                }
+                "v2_factory" => result.v2_factory = parse_address(value).map(|a| address(&a)).ok_or_else(invalid)?,
+                "error_pool" if value.is_empty() => result.error_pool = vec![],
+                "error_pool" => result.error_pool = parse_address(value).map(|a| address(&a)).ok_or_else(invalid)?,
+                "weth" => result.weth = parse_address(value).ok_or_else(invalid)?,
+                "usd_token" => result.usd_token = parse_address(value).ok_or_else(invalid)?,
+                "usd_pool" => result.usd_pool = parse_address(value).ok_or_else(invalid)?,
+                "stable_coins" => result.stable_coins = parse_addresses(value).ok_or_else(invalid)?,
+                "whitelist_tokens" => result.whitelist_tokens = parse_addresses(value).ok_or_else(invalid)?,
+                "minimum_eth_locked" => result.minimum_eth_locked = value.parse().map_err(|_| invalid())?,
+                "bootstrap_block" => result.bootstrap_block = value.parse().map_err(|_| invalid())?,
+                _ if module_keys.contains(&key) => {}
+                _ => return Err(anyhow::anyhow!("unknown param `{}`", key)),
+            }
+        }
+
+        let forks = fork_deployments(chain);
+        result.profiles = protocols
+            .into_iter()
+            .map(|protocol| match protocol {
+                Protocol::UniswapV3 => Ok(result.uniswap_v3_profile()),
+                _ => forks
+                    .iter()
+                    .find(|profile| profile.protocol == protocol)
+                    .cloned()
+                    .ok_or_else(|| anyhow::anyhow!("{} is not deployed on `{}`", protocol.name(), chain)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if uniswap_v4 && result.pool_manager.is_empty() {
+            result.pool_manager =
+                v4_deployment(chain).ok_or_else(|| anyhow::anyhow!("uniswap_v4 is not deployed on `{}`", chain))?;
+        }
+        Ok(result)
+    }
+
+    fn uniswap_v3_profile(&self) -> Profile {
+        Profile {
+            protocol: Protocol::UniswapV3,
+            factory: self.factory.clone(),
+            position_manager: self.position_manager.clone(),
+        }
+    }
+
+    /// Profile of the `protocol` of a pool.
+    pub fn profile(&self, protocol: &str) -> Option<&Profile> {
+        let protocol = Protocol::from_name(protocol)?;
+        self.profiles.iter().find(|profile| profile.protocol == protocol)
+    }
+
+    pub fn factories(&self) -> Vec<&[u8]> {
+        self.profiles.iter().map(|profile| profile.factory.as_slice()).collect()
+    }
+
+    pub fn profile_of_factory(&self, factory: &[u8]) -> Option<&Profile> {
+        self.profiles.iter().find(|profile| profile.factory == factory)
+    }
+
+    pub fn is_stable_coin(&self, token: &str) -> bool {
+        self.stable_coins.iter().any(|t| t == token)
+    }
+
+    pub fn is_whitelisted(&self, token: &str) -> bool {
+        self.whitelist_tokens.iter().any(|t| t == token)
+    }
+
+    /// Side of `usd_token` in `usd_pool`, pool tokens being sorted by address.
+    pub fn usd_token_side(&self) -> &'static str {
+        match self.usd_token < self.weth {
+            true => "token0",
+            false => "token1",
+        }
+    }
+
+    /// Id of the `Factory` entity, the checksummed factory address.
+    pub fn factory_id(&self) -> String {
+        checksum_address(&self.factory)
+    }
+}
+
+/// Lowercase address without `0x`, `None` unless it is 20 bytes of hex.
+fn parse_address(value: &str) -> Option<String> {
+    let value = value.trim().trim_start_matches("0x").to_lowercase();
+    match value.len() == 40 && value.chars().all(|c| c.is_ascii_hexdigit()) {
+        true => Some(value),
+        false => None,
+    }
+}
+
+fn parse_addresses(value: &str) -> Option<Vec<String>> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|a| !a.is_empty())
+        .map(parse_address)
+        .collect()
+}
+
+fn address(value: &str) -> Vec<u8> {
+    hex::decode(value).unwrap()
+}
+
+/// EIP-55 mixed-case encoding of an address, with `0x`.
+pub fn checksum_address(address: &[u8]) -> String {
+    let lower = hex::encode(address);
+    let mut hash = [0u8; 32];
+    let mut hasher = Keccak::v256();
+    hasher.update(lower.as_bytes());
+    hasher.finalize(&mut hash);
+
+    let checksummed: String = lower
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let nibble = (hash[i / 2] >> if i % 2 == 0 { 4 } else { 0 }) & 0x0f;
+            match nibble >= 8 {
+                true => c.to_ascii_uppercase(),
+                false => c,
+            }
+        })
+        .collect();
+    format!("0x{checksummed}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_presets() {
+        let ethereum = Config::parse("").unwrap();
+        assert_eq!(Config::preset("ethereum").unwrap(), ethereum);
+        assert_eq!("token0", ethereum.usd_token_side());
+        assert_eq!("0x1F98431c8aD98523631AE4a59f267346ea31F984", ethereum.factory_id());
+        assert_eq!(12369621, ethereum.bootstrap_block);
+        assert!(ethereum.is_whitelisted("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"));
+
+        let arbitrum = Config::parse("chain=arbitrum-one").unwrap();
+        assert_eq!("token1", arbitrum.usd_token_side());
+        assert!(arbitrum.error_pool.is_empty());
+        assert!(arbitrum.is_stable_coin("ff970a61a04b1ca14834a43f5de4533ebddb5cc8"));
+        assert!(!arbitrum.is_stable_coin("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"));
+
+        assert!(Config::parse("chain=solana").is_err());
+        assert_eq!(1, ethereum.profiles.len());
+        assert_eq!(Some(Protocol::UniswapV3), ethereum.profile("").map(|p| p.protocol));
+        assert!(ethereum.profile("pancakeswap_v3").is_none());
+    }
+
+    #[test]
+    fn test_parse_protocols() {
+        let config = Config::parse(
+            "protocols=uniswap_v3,pancakeswap_v3,sushiswap_v3&position_manager=0000000000000000000000000000000000000001",
+        )
+        .unwrap();
+        let protocols: Vec<Protocol> = config.profiles.iter().map(|p| p.protocol).collect();
+        assert_eq!(
+            vec![Protocol::UniswapV3, Protocol::PancakeswapV3, Protocol::SushiswapV3],
+            protocols
+        );
+        assert_eq!(config.position_manager, config.profiles[0].position_manager);
+        let pancake = config.profile("pancakeswap_v3").unwrap();
+        assert_eq!(Some(pancake), config.profile_of_factory(&pancake.factory));
+        assert!(Config::parse("chain=arbitrum-one&protocols=sushiswap_v3").is_ok());
+        assert!(config.pool_manager.is_empty());
+
+        let config = Config::parse("chain=arbitrum-one&protocols=uniswap_v3,uniswap_v4").unwrap();
+        assert_eq!(1, config.profiles.len());
+        assert_eq!(address("360e68faccca8ca495c1b759fd9eee466db9fb32"), config.pool_manager);
+    }
+
+    #[test]
+    fn test_parse_overrides() {
+        let config = Config::parse(
+            "chain=arbitrum-one&minimum_eth_locked=10&error_pool=&whitelist_tokens=0x82AF49447D8a07e3bd95BD0d56f35241523fBab1, 912ce59144191c1204e64559fe8253a0e49e6548",
+        )
+        .unwrap();
+        assert_eq!(10, config.minimum_eth_locked);
+        assert_eq!(
+            vec![
+                "82af49447d8a07e3bd95bd0d56f35241523fbab1".to_string(),
+                "912ce59144191c1204e64559fe8253a0e49e6548".to_string()
+            ],
+            config.whitelist_tokens
+        );
+
+        assert!(Config::parse("weth=c02aaa39").is_err());
+        assert!(Config::parse("protocols=balancer_v2").is_err());
+        assert!(Config::parse("band_bps=200").is_err());
+        assert_eq!(
+            Config::parse("").unwrap(),
+            Config::parse_with("band_bps=200", &["band_bps"]).unwrap()
+        );
+    }
+}
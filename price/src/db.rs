@@ -10,6 +10,8 @@ use substreams::store::{
 use substreams::{log, Hex};
 use substreams_entity_change::tables::Tables;
 
+use crate::config::Config;
+use crate::math;
 use crate::pb::uniswap::events::pool_event::Type::{Burn as BurnEvent, Mint as MintEvent, Swap as SwapEvent};
 use crate::pb::uniswap::events::position_event::Type;
 use crate::pb::uniswap::events::{IncreaseLiquidityPosition, PoolSqrtPrice, PositionEvent};
@@ -38,13 +40,11 @@ pub fn bundle_store_eth_price_usd_bundle_entity_change(
 // -------------------
 //  Map Factory Entities
 // -------------------
-pub fn factory_created_factory_entity_change(tables: &mut Tables) {
-    let id = "0x1F98431c8aD98523631AE4a59f267346ea31F984";
-
+pub fn factory_created_factory_entity_change(tables: &mut Tables, factory_id: &str) {
     let bigint0 = BigInt::zero();
     let bigdecimal0 = BigDecimal::zero();
     tables
-        .create_row("Factory", id)
+        .create_row("Factory", factory_id)
         .set("poolCount", &bigint0)
         .set("txCount", &bigint0)
         .set("totalVolumeUSD", &bigdecimal0)
@@ -59,22 +59,31 @@ pub fn factory_created_factory_entity_change(tables: &mut Tables) {
         .set("owner", &format!("0x{}", Hex(utils::ZERO_ADDRESS).to_string()));
 }
 
-pub fn pool_created_factory_entity_change(tables: &mut Tables, pool_count_deltas: &Deltas<DeltaBigInt>) {
+pub fn pool_created_factory_entity_change(
+    tables: &mut Tables,
+    pool_count_deltas: &Deltas<DeltaBigInt>,
+    factory_id: &str,
+) {
     pool_count_deltas.iter().for_each(|delta| {
-        let id = "0x1F98431c8aD98523631AE4a59f267346ea31F984".to_string();
-        tables.update_row("Factory", &id).set("poolCount", &delta.new_value);
+        tables
+            .update_row("Factory", factory_id)
+            .set("poolCount", &delta.new_value);
     })
 }
 
-pub fn tx_count_factory_entity_change(tables: &mut Tables, tx_count_deltas: &Deltas<DeltaBigInt>) {
+pub fn tx_count_factory_entity_change(tables: &mut Tables, tx_count_deltas: &Deltas<DeltaBigInt>, factory_id: &str) {
     for delta in tx_count_deltas.iter().key_first_segment_eq("factory") {
         tables
-            .update_row("Factory", "0x1F98431c8aD98523631AE4a59f267346ea31F984")
+            .update_row("Factory", factory_id)
             .set("txCount", &delta.new_value);
     }
 }
 
-pub fn swap_volume_factory_entity_change(tables: &mut Tables, swaps_volume_deltas: &Deltas<DeltaBigDecimal>) {
+pub fn swap_volume_factory_entity_change(
+    tables: &mut Tables,
+    swaps_volume_deltas: &Deltas<DeltaBigDecimal>,
+    factory_id: &str,
+) {
     for delta in swaps_volume_deltas
         .iter()
         .key_first_segment_eq("factory")
@@ -87,12 +96,16 @@ pub fn swap_volume_factory_entity_change(tables: &mut Tables, swaps_volume_delta
         ])
     {
         tables
-            .update_row("Factory", "0x1F98431c8aD98523631AE4a59f267346ea31F984")
+            .update_row("Factory", factory_id)
             .set(key::last_segment(&delta.key), &delta.new_value);
     }
 }
 
-pub fn tvl_factory_entity_change(tables: &mut Tables, derived_factory_tvl_deltas: &Deltas<DeltaBigDecimal>) {
+pub fn tvl_factory_entity_change(
+    tables: &mut Tables,
+    derived_factory_tvl_deltas: &Deltas<DeltaBigDecimal>,
+    factory_id: &str,
+) {
     for delta in derived_factory_tvl_deltas
         .iter()
         .key_first_segment_eq("factory")
@@ -104,7 +117,7 @@ pub fn tvl_factory_entity_change(tables: &mut Tables, derived_factory_tvl_deltas
         ])
     {
         tables
-            .update_row("Factory", "0x1F98431c8aD98523631AE4a59f267346ea31F984")
+            .update_row("Factory", factory_id)
             .set(key::last_segment(&delta.key), &delta.new_value);
     }
 }
@@ -128,6 +141,7 @@ fn create_pool(tables: &mut Tables, pool: &Pool) {
         .set("token0", format!("0x{}", pool.token0.as_ref().unwrap().address))
         .set("token1", format!("0x{}", pool.token1.as_ref().unwrap().address))
         .set_bigint("feeTier", &pool.fee_tier)
+        .set("protocol", pool.protocol_name().to_string())
         .set("liquidity", &bigint0)
         .set("sqrtPrice", &bigint0)
         .set("feeGrowthGlobal0X128", &bigint0)
@@ -297,6 +311,9 @@ pub fn swap_volume_pool_entity_change(tables: &mut Tables, swaps_volume_deltas:
             "volumeUSD" => "volumeUSD",
             "volumeUntrackedUSD" => "untrackedVolumeUSD",
             "feesUSD" => "feesUSD",
+            "collectedFeesToken0" => "collectedFeesToken0",
+            "collectedFeesToken1" => "collectedFeesToken1",
+            "collectedFeesUSD" => "collectedFeesUSD",
             "liquidityProviderCount" => "liquidityProviderCount",
             _ => continue,
         };
@@ -562,104 +579,164 @@ pub fn liquidities_tick_entity_change(tables: &mut Tables, ticks_liquidities_del
 // -----------------------
 //  Map Tick Day/Hour data
 // -----------------------
-// pub fn create_entity_tick_windows(tables: &mut Tables, ticks_created: &Vec<events::TickCreated>) {
-//     for tick in ticks_created {
-//         let day_id = tick.created_at_timestamp / 86400;
-//         let hour_id = tick.created_at_timestamp / 3600;
-//
-//         log::info!("create_entity_tick_windows hour_id {}", hour_id);
-//
-//         let pool_address = &tick.pool_address;
-//         let tick_idx = &tick.idx;
-//         create_tick_windows(tables, "TickDayData", pool_address.as_str(), tick_idx, day_id);
-//         // create_tick_windows(tables, "TickHourData", pool_address.as_str(), tick_idx, hour_id);
-//     }
-// }
-//
-// pub fn update_tick_windows(tables: &mut Tables, ticks_updated: &Vec<events::TickUpdated>) {
-//     for tick in ticks_updated {
-//         let day_id = tick.timestamp / 86400;
-//         let hour_id = tick.timestamp / 3600;
-//
-//         log::info!("update_tick_windows hour_id {}", hour_id);
-//
-//         let tick_idx = &tick.idx;
-//         let pool_address = &tick.pool_address;
-//
-//         if tick.fee_growth_outside_0x_128.len() != 0 {
-//             tables
-//                 .update_row("TickDayData", format!("0x{pool_address}#{tick_idx}-{day_id}"))
-//                 .set_bigint("feeGrowthOutside0X128", &tick.fee_growth_outside_0x_128);
-//             // tables
-//             //     .update_row("TickHourData", format!("0x{pool_address}#{tick_idx}-{hour_id}"))
-//             //     .set_bigint("feeGrowthOutside0X128", &tick.fee_growth_outside_0x_128);
-//         }
-//         if tick.fee_growth_outside_1x_128.len() != 0 {
-//             tables
-//                 .update_row("TickDayData", format!("0x{pool_address}#{tick_idx}-{day_id}"))
-//                 .set_bigint("feeGrowthOutside1X128", &tick.fee_growth_outside_1x_128);
-//             // tables
-//             //     .update_row("TickHourData", format!("0x{pool_address}#{tick_idx}-{hour_id}"))
-//             //     .set_bigint("feeGrowthOutside1X128", &tick.fee_growth_outside_1x_128);
-//         }
-//     }
-// }
-//
-// pub fn liquidities_tick_windows(tables: &mut Tables, ticks_liquidities_deltas: &Deltas<DeltaBigInt>) {
-//     for delta in ticks_liquidities_deltas.iter() {
-//         let table_name = match key::first_segment(&delta.key) {
-//             "TickDayData" => "TickDayData",
-//             // "TickHourData" => "TickHourData",
-//             _ => continue,
-//         };
-//         let time_id = key::segment_at(&delta.key, 1);
-//
-//         log::info!("liquidities_tick_windows time_id {}", time_id);
-//
-//         let pool_address = key::segment_at(&delta.key, 2);
-//         let tick_idx = key::segment_at(&delta.key, 3);
-//
-//         let field_name = match key::last_segment(&delta.key) {
-//             "liquidityNet" => "liquidityNet",
-//             "liquidityGross" => "liquidityGross",
-//             _ => continue,
-//         };
-//
-//         tables
-//             .update_row(table_name, format!("0x{pool_address}#{tick_idx}-{time_id}"))
-//             .set(field_name, &delta.new_value);
-//     }
-// }
-//
-// fn create_tick_windows(tables: &mut Tables, table_name: &str, pool_address: &str, tick_idx: &String, time_id: u64) {
-//     let bigdecimal0 = BigDecimal::from(0);
-//     let bigint0 = BigInt::from(0);
-//
-//     // We cannot determine when a new Tick is created. If a given tick idx was initialized
-//     // in the past. In the future the same tick idx can be re-used for an event.
-//     let row = tables
-//         .update_row(table_name, format!("0x{pool_address}#{tick_idx}-{time_id}"))
-//         .set("pool", &format!("0x{pool_address}"))
-//         .set("tick", &format!("0x{pool_address}#{tick_idx}"))
-//         .set("liquidityGross", &bigint0)
-//         .set("liquidityNet", &bigint0)
-//         .set("volumeToken0", &bigdecimal0)
-//         .set("volumeToken1", &bigdecimal0)
-//         .set("volumeUSD", &bigdecimal0)
-//         .set("feesUSD", &bigdecimal0);
-//
-//     match table_name {
-//         "TickDayData" => {
-//             row.set("date", (time_id * 86400) as i32);
-//             row.set("feeGrowthOutside0X128", &bigint0);
-//             row.set("feeGrowthOutside1X128", &bigint0);
-//         }
-//         "TickHourData" => {
-//             row.set("periodStartUnix", (time_id * 3600) as i32);
-//         }
-//         _ => {}
-//     }
-// }
+// A tick window is created by the first change of its liquidities or volumes in the period, the
+// liquidities it starts with being the ones of the tick at that time
+pub fn create_entity_tick_windows(
+    tables: &mut Tables,
+    ticks_liquidities_deltas: &Deltas<DeltaBigInt>,
+    swaps_volume_deltas: &Deltas<DeltaBigDecimal>,
+    ticks_liquidities_store: &StoreGetBigInt,
+    swaps_volume_store: &StoreGetBigDecimal,
+) {
+    let keys = tick_windows_to_create(
+        ticks_liquidities_deltas,
+        swaps_volume_deltas,
+        |ordinal, key| ticks_liquidities_store.has_at(ordinal, key),
+        |ordinal, key| swaps_volume_store.has_at(ordinal, key),
+    );
+    for key in keys {
+        let (table_name, time_id, pool_address) = pool_windows_id_fields(key);
+        let tick_idx = key::segment_at(key, 3);
+        create_tick_windows(
+            tables,
+            table_name,
+            time_id.parse::<u64>().unwrap(),
+            pool_address,
+            tick_idx,
+            ticks_liquidities_store,
+        );
+    }
+}
+
+// Keys of the tick windows opened by this block. A window is opened once, by the liquidities or
+// the volumes changing first: the window opened by the other ones earlier in the period is kept,
+// since opening it again would reset their fields
+fn tick_windows_to_create<'a>(
+    ticks_liquidities_deltas: &'a Deltas<DeltaBigInt>,
+    swaps_volume_deltas: &'a Deltas<DeltaBigDecimal>,
+    has_liquidities: impl Fn(u64, &str) -> bool,
+    has_volumes: impl Fn(u64, &str) -> bool,
+) -> Vec<&'a str> {
+    let window_key = |key: &str, field: &str| format!("{}:{field}", &key[..key.rfind(':').unwrap()]);
+    let liquidities_keys = ticks_liquidities_deltas
+        .iter()
+        .key_first_segment_in(["TickDayData", "TickHourData"])
+        .key_last_segment_eq("liquidityGross")
+        .operation_eq(Operation::Create)
+        .filter(|delta| !has_volumes(delta.ordinal, &window_key(&delta.key, "volumeToken0")))
+        .map(|delta| delta.key.as_str());
+    let volumes_keys = swaps_volume_deltas
+        .iter()
+        .key_first_segment_in(["TickDayData", "TickHourData"])
+        .key_last_segment_eq("volumeToken0")
+        .operation_eq(Operation::Create)
+        .filter(|delta| !has_liquidities(delta.ordinal, &window_key(&delta.key, "liquidityGross")))
+        .map(|delta| delta.key.as_str());
+
+    liquidities_keys.chain(volumes_keys).collect()
+}
+
+pub fn update_tick_windows(tables: &mut Tables, ticks_updated: &Vec<events::TickUpdated>) {
+    for tick in ticks_updated {
+        let day_id = tick.timestamp / 86400;
+        let tick_idx = &tick.idx;
+        let pool_address = &tick.pool_address;
+
+        let row = tables.update_row("TickDayData", format!("0x{pool_address}#{tick_idx}-{day_id}"));
+        if tick.fee_growth_outside_0x_128.len() != 0 {
+            row.set_bigint("feeGrowthOutside0X128", &tick.fee_growth_outside_0x_128);
+        }
+        if tick.fee_growth_outside_1x_128.len() != 0 {
+            row.set_bigint("feeGrowthOutside1X128", &tick.fee_growth_outside_1x_128);
+        }
+    }
+}
+
+// The liquidities of a tick window are the ones of the tick at the end of the period, not the
+// changes within the period which are only used to open the window
+pub fn liquidities_tick_windows(tables: &mut Tables, timestamp: i64, ticks_liquidities_deltas: &Deltas<DeltaBigInt>) {
+    let day_id = timestamp / 86400;
+    let hour_id = timestamp / 3600;
+
+    for delta in ticks_liquidities_deltas
+        .iter()
+        .key_first_segment_eq("tick")
+        .key_last_segment_in(["liquidityNet", "liquidityGross"])
+    {
+        let pool_address = key::segment_at(&delta.key, 1);
+        let tick_idx = key::segment_at(&delta.key, 2);
+        let field_name = key::last_segment(&delta.key);
+
+        tables
+            .update_row("TickDayData", format!("0x{pool_address}#{tick_idx}-{day_id}"))
+            .set(field_name, &delta.new_value);
+        tables
+            .update_row("TickHourData", format!("0x{pool_address}#{tick_idx}-{hour_id}"))
+            .set(field_name, &delta.new_value);
+    }
+}
+
+pub fn swap_volume_tick_windows(tables: &mut Tables, swaps_volume_deltas: &Deltas<DeltaBigDecimal>) {
+    for delta in swaps_volume_deltas
+        .iter()
+        .key_first_segment_in(["TickDayData", "TickHourData"])
+        .key_last_segment_in(["volumeToken0", "volumeToken1", "volumeUSD", "feesUSD"])
+        .operation_not_eq(Operation::Delete)
+    {
+        let (table_name, time_id, pool_address) = pool_windows_id_fields(&delta.key);
+        let tick_idx = key::segment_at(&delta.key, 3);
+
+        tables
+            .update_row(table_name, format!("0x{pool_address}#{tick_idx}-{time_id}"))
+            .set(key::last_segment(&delta.key), &delta.new_value);
+    }
+}
+
+fn create_tick_windows(
+    tables: &mut Tables,
+    table_name: &str,
+    time_id: u64,
+    pool_address: &str,
+    tick_idx: &str,
+    ticks_liquidities_store: &StoreGetBigInt,
+) {
+    let bigdecimal0 = BigDecimal::from(0);
+    let bigint0 = BigInt::from(0);
+
+    let liquidity_gross = ticks_liquidities_store
+        .get_last(format!("tick:{pool_address}:{tick_idx}:liquidityGross"))
+        .unwrap_or_default();
+    let liquidity_net = ticks_liquidities_store
+        .get_last(format!("tick:{pool_address}:{tick_idx}:liquidityNet"))
+        .unwrap_or_default();
+    let price0 = math::compute_price_from_tick_idx(tick_idx.parse::<i32>().unwrap());
+    let price1 = math::safe_div(&BigDecimal::from(1 as i32), &price0);
+
+    let row = tables
+        .update_row(table_name, format!("0x{pool_address}#{tick_idx}-{time_id}"))
+        .set("pool", &format!("0x{pool_address}"))
+        .set("tick", &format!("0x{pool_address}#{tick_idx}"))
+        .set("liquidityGross", &liquidity_gross)
+        .set("liquidityNet", &liquidity_net)
+        .set("price0", &price0)
+        .set("price1", &price1)
+        .set("volumeToken0", &bigdecimal0)
+        .set("volumeToken1", &bigdecimal0)
+        .set("volumeUSD", &bigdecimal0)
+        .set("feesUSD", &bigdecimal0);
+
+    match table_name {
+        "TickDayData" => {
+            row.set("date", (time_id * 86400) as i32);
+            row.set("feeGrowthOutside0X128", &bigint0);
+            row.set("feeGrowthOutside1X128", &bigint0);
+        }
+        "TickHourData" => {
+            row.set("periodStartUnix", (time_id * 3600) as i32);
+        }
+        _ => {}
+    }
+}
 
 // --------------------
 //  Map Position Entities
@@ -1000,7 +1077,8 @@ pub fn swaps_mints_burns_created_entity_change(
     tables: &mut Tables,
     pool_events: &Vec<events::PoolEvent>,
     tx_count_store: StoreGetBigInt,
-    store_eth_prices: StoreGetBigDecimal,
+    store_eth_prices: &StoreGetBigDecimal,
+    config: &Config,
 ) {
     for pool_event in pool_events {
         if pool_event.r#type.is_none() {
@@ -1048,6 +1126,7 @@ pub fn swaps_mints_burns_created_entity_change(
                         &amount0_abs,
                         &amount1_abs,
                         &bundle_eth_price, // get the value from the store_eth_price
+                        config,
                     )
                     .div(BigDecimal::from(2 as i32));
 
@@ -1134,21 +1213,52 @@ pub fn swaps_mints_burns_created_entity_change(
 // --------------------
 //  Map Flashes Entities
 // --------------------
-// pub fn flashes_update_pool_fee_entity_change(tables: &mut Tables, flashes: Vec<events::Flash>) {
-//     // TODO: wut? flash updates would affect `fee_growth_global_0x_128` and `fee_growth_global_1x_128`?
-//     //  it's the business of TickUpdate, not of Flashes. Don't flashes produce some such updates?
-//     for flash in flashes {
-//         // tables.update_row("Pool", flash.pool_address.as_str());
-//         // .set(
-//         //     "feeGrowthGlobal0X128",
-//         //     BigInt::from(flash.fee_growth_global_0x_128.unwrap()),
-//         // )
-//         // .set(
-//         //     "feeGrowthGlobal1X128",
-//         //     BigInt::from(flash.fee_growth_global_1x_128.unwrap()),
-//         // );
-//     }
-// }
+pub fn flashes_created_entity_change(
+    tables: &mut Tables,
+    flashes: &Vec<events::Flash>,
+    store_eth_prices: &StoreGetBigDecimal,
+) {
+    // the fees paid by the flashes reach the pool through the fee growth global updates
+    for flash in flashes {
+        let ord = flash.log_ordinal;
+        let token0_addr = &flash.token0;
+        let token1_addr = &flash.token1;
+        let transaction_id = &flash.transaction_id;
+
+        let token0_derived_eth_price = store_eth_prices
+            .get_at(ord, format!("token:{token0_addr}:dprice:eth"))
+            .unwrap_or_default();
+        let token1_derived_eth_price = store_eth_prices
+            .get_at(ord, format!("token:{token1_addr}:dprice:eth"))
+            .unwrap_or_default();
+        let bundle_eth_price = store_eth_prices.get_at(ord, "bundle").unwrap_or_default();
+
+        let amount0 = BigDecimal::try_from(flash.amount_0.as_str()).unwrap();
+        let amount1 = BigDecimal::try_from(flash.amount_1.as_str()).unwrap();
+        let amount_usd: BigDecimal = utils::calculate_amount_usd(
+            &amount0,
+            &amount1,
+            &token0_derived_eth_price,
+            &token1_derived_eth_price,
+            &bundle_eth_price,
+        );
+
+        // flashes are not part of the pool transaction count, their log index numbers them
+        tables
+            .create_row("Flash", &format!("0x{transaction_id}#{}", flash.log_index))
+            .set("transaction", format!("0x{transaction_id}"))
+            .set("timestamp", flash.timestamp)
+            .set("pool", format!("0x{}", flash.pool_address))
+            .set("sender", &hex::decode(&flash.sender).unwrap())
+            .set("recipient", &hex::decode(&flash.recipient).unwrap())
+            .set("amount0", amount0)
+            .set("amount1", amount1)
+            .set("amountUSD", amount_usd)
+            .set("amount0Paid", BigDecimal::try_from(flash.paid_0.as_str()).unwrap())
+            .set("amount1Paid", BigDecimal::try_from(flash.paid_1.as_str()).unwrap())
+            .set("logIndex", flash.log_index);
+    }
+}
 
 // --------------------
 //  Map Uniswap Day Data Entities
@@ -1753,3 +1863,80 @@ pub fn prices_close_token_windows(tables: &mut Tables, eth_prices_deltas: &Delta
             .set("close", &delta.old_value);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POOL: &str = "8ad599c3a0ff1de082011efddc58f1908eb6e6d8";
+
+    fn liquidity_delta(ordinal: u64, key: String) -> DeltaBigInt {
+        DeltaBigInt {
+            operation: Operation::Create,
+            ordinal,
+            key,
+            old_value: BigInt::zero(),
+            new_value: BigInt::from(1000),
+        }
+    }
+
+    fn volume_delta(ordinal: u64, key: String) -> DeltaBigDecimal {
+        DeltaBigDecimal {
+            operation: Operation::Create,
+            ordinal,
+            key,
+            old_value: BigDecimal::zero(),
+            new_value: BigDecimal::from(5),
+        }
+    }
+
+    #[test]
+    fn test_tick_window_of_a_swap_then_a_mint() {
+        let liquidity_key = format!("TickDayData:19800:{POOL}:-60:liquidityGross");
+        let volume_key = format!("TickDayData:19800:{POOL}:-60:volumeToken0");
+        let no_liquidities = Deltas { deltas: vec![] };
+        let no_volumes = Deltas { deltas: vec![] };
+
+        // a swap opens the window
+        let volumes = Deltas {
+            deltas: vec![volume_delta(10, volume_key.clone())],
+        };
+        let keys = tick_windows_to_create(&no_liquidities, &volumes, |_, _| false, |_, _| false);
+        assert_eq!(vec![volume_key.as_str()], keys);
+
+        // a mint on the same tick later in the day keeps the window and its volumes
+        let liquidities = Deltas {
+            deltas: vec![liquidity_delta(20, liquidity_key.clone())],
+        };
+        let keys = tick_windows_to_create(&liquidities, &no_volumes, |_, _| false, |_, key| key == volume_key);
+        assert!(keys.is_empty());
+
+        // both in the same block, the swap comes first
+        let keys = tick_windows_to_create(
+            &liquidities,
+            &volumes,
+            |ordinal, key| key == liquidity_key && ordinal >= 20,
+            |ordinal, key| key == volume_key && ordinal >= 10,
+        );
+        assert_eq!(vec![volume_key.as_str()], keys);
+    }
+
+    #[test]
+    fn test_tick_window_of_a_mint_then_a_swap() {
+        let liquidity_key = format!("TickHourData:475200:{POOL}:0:liquidityGross");
+        let volume_key = format!("TickHourData:475200:{POOL}:0:volumeToken0");
+        let liquidities = Deltas {
+            deltas: vec![liquidity_delta(10, liquidity_key.clone())],
+        };
+        let volumes = Deltas {
+            deltas: vec![volume_delta(20, volume_key.clone())],
+        };
+        let keys = tick_windows_to_create(
+            &liquidities,
+            &volumes,
+            |ordinal, key| key == liquidity_key && ordinal >= 10,
+            |ordinal, key| key == volume_key && ordinal >= 20,
+        );
+        assert_eq!(vec![liquidity_key.as_str()], keys);
+    }
+}
@@ -1,9 +1,10 @@
 use crate::math::compute_price_from_tick_idx;
 use crate::pb::uniswap::events;
+use crate::protocol::{Profile, Protocol};
+use crate::storage::pool_manager::PoolManagerStorage;
 use crate::storage::position_manager::PositionManagerStorage;
 use crate::storage::uniswap_v3_pool::UniswapPoolStorage;
-use crate::utils::NON_FUNGIBLE_POSITION_MANAGER;
-use crate::{abi, math, utils, BurnEvent, EventTrait, MintEvent, Pool, SwapEvent};
+use crate::{abi, math, utils, v4, BurnEvent, EventTrait, MintEvent, Pool, SwapEvent};
 use substreams::prelude::{BigDecimal, BigInt};
 use substreams::{log, Hex};
 use substreams_ethereum::block_view::CallView;
@@ -25,7 +26,10 @@ pub fn extract_pool_events_and_positions(
     pool: &Pool,
     timestamp_seconds: u64,
     block_number: u64,
+    profile: &Profile,
 ) {
+    let protocol = profile.protocol;
+    let position_manager = profile.position_manager.as_slice();
     let common_tick_updated = events::TickUpdated {
         log_ordinal: log.ordinal,
         pool_address: pool.address.to_string(),
@@ -33,7 +37,7 @@ pub fn extract_pool_events_and_positions(
         ..Default::default()
     };
 
-    if let Some(swap) = abi::pool::events::Swap::match_and_decode(log) {
+    if let Some(swap) = protocol.decode_swap(log) {
         log::info!("SWAP: transaction: {}", transaction_id.to_string());
         if !pool.should_handle_swap() {
             return;
@@ -57,6 +61,7 @@ pub fn extract_pool_events_and_positions(
             transaction_id: transaction_id.to_string(),
             timestamp: timestamp_seconds,
             created_at_block_number: block_number,
+            protocol: protocol.name().to_string(),
             r#type: Some(SwapEvent(events::pool_event::Swap {
                 sender: Hex(&swap.sender).to_string(),
                 recipient: Hex(&swap.recipient).to_string(),
@@ -78,6 +83,7 @@ pub fn extract_pool_events_and_positions(
                 collect_positions,
                 transfer_positions,
                 &position_manager_contract_call,
+                position_manager,
             );
         }
     } else if let Some(mint) = abi::pool::events::Mint::match_and_decode(log) {
@@ -101,6 +107,7 @@ pub fn extract_pool_events_and_positions(
             transaction_id: transaction_id.to_string(),
             timestamp: timestamp_seconds,
             created_at_block_number: block_number,
+            protocol: protocol.name().to_string(),
             r#type: Some(MintEvent(events::pool_event::Mint {
                 owner: Hex(&mint.owner).to_string(),
                 sender: Hex(&mint.sender).to_string(),
@@ -136,7 +143,8 @@ pub fn extract_pool_events_and_positions(
         upper_tick.price1 = price1.into();
         ticks_created.push(upper_tick);
 
-        let storage = UniswapPoolStorage::new(&call_view.call.storage_changes, &log.address);
+        let storage =
+            UniswapPoolStorage::with_layout(&call_view.call.storage_changes, &log.address, protocol.pool_layout());
 
         ticks_updated.push(events::TickUpdated {
             idx: mint.tick_upper.as_ref().into(),
@@ -152,7 +160,7 @@ pub fn extract_pool_events_and_positions(
         });
 
         if let Some(position_manager_contract_call) = call_view.parent() {
-            if position_manager_contract_call.address != NON_FUNGIBLE_POSITION_MANAGER {
+            if position_manager_contract_call.address != position_manager {
                 return;
             }
 
@@ -204,6 +212,7 @@ pub fn extract_pool_events_and_positions(
                 collect_positions,
                 transfer_positions,
                 &position_manager_contract_call,
+                position_manager,
             );
         }
     } else if let Some(burn) = abi::pool::events::Burn::match_and_decode(log) {
@@ -230,6 +239,7 @@ pub fn extract_pool_events_and_positions(
             transaction_id: transaction_id.to_string(),
             timestamp: timestamp_seconds,
             created_at_block_number: block_number,
+            protocol: protocol.name().to_string(),
             r#type: Some(BurnEvent(events::pool_event::Burn {
                 owner: Hex(&burn.owner).to_string(),
                 origin: origin.to_string(),
@@ -241,7 +251,8 @@ pub fn extract_pool_events_and_positions(
             })),
         });
 
-        let storage = UniswapPoolStorage::new(&call_view.call.storage_changes, &log.address);
+        let storage =
+            UniswapPoolStorage::with_layout(&call_view.call.storage_changes, &log.address, protocol.pool_layout());
 
         ticks_updated.push(events::TickUpdated {
             idx: burn.tick_upper.as_ref().into(),
@@ -264,6 +275,7 @@ pub fn extract_pool_events_and_positions(
                 collect_positions,
                 transfer_positions,
                 &position_manager_contract_call,
+                position_manager,
             );
         }
     } else if abi::pool::events::Collect::match_log(log) {
@@ -275,6 +287,7 @@ pub fn extract_pool_events_and_positions(
                 collect_positions,
                 transfer_positions,
                 &position_manager_contract_call,
+                position_manager,
             );
         };
     }
@@ -307,9 +320,10 @@ pub fn extract_pool_liquidities(
         return;
     }
 
-    let storage = UniswapPoolStorage::new(storage_changes, &log.address);
+    let protocol = pool.protocol();
+    let storage = UniswapPoolStorage::with_layout(storage_changes, &log.address, protocol.pool_layout());
 
-    if abi::pool::events::Swap::match_log(&log) {
+    if protocol.match_swap(&log) {
         if !pool.should_handle_swap() {
             return;
         }
@@ -363,8 +377,9 @@ pub fn extract_fee_growth_update(
     storage_changes: &Vec<StorageChange>,
     pool: &Pool,
 ) {
+    let protocol = pool.protocol();
     let mut do_extract = false;
-    if abi::pool::events::Swap::match_log(&log) {
+    if protocol.match_swap(&log) {
         if !pool.should_handle_swap() {
             return;
         }
@@ -380,18 +395,24 @@ pub fn extract_fee_growth_update(
         }
         do_extract = true;
     } else if abi::pool::events::Flash::match_log(&log) {
-        do_extract = false; //TODO: handle this later when we process flashes
+        do_extract = true;
     }
     if do_extract {
         fee_growth_updates.append(&mut utils::extract_pool_fee_growth_global_updates(
             log.ordinal,
             &log.address,
             storage_changes,
+            protocol.pool_layout(),
         ));
     }
 }
 
-pub fn extract_pool_sqrt_prices(pool_sqrt_prices: &mut Vec<events::PoolSqrtPrice>, log: &Log, pool_address: &String) {
+pub fn extract_pool_sqrt_prices(
+    pool_sqrt_prices: &mut Vec<events::PoolSqrtPrice>,
+    log: &Log,
+    pool_address: &String,
+    protocol: Protocol,
+) {
     if let Some(event) = abi::pool::events::Initialize::match_and_decode(log) {
         pool_sqrt_prices.push(events::PoolSqrtPrice {
             pool_address: pool_address.to_string(),
@@ -400,7 +421,7 @@ pub fn extract_pool_sqrt_prices(pool_sqrt_prices: &mut Vec<events::PoolSqrtPrice
             tick: event.tick.into(),
             initialized: true,
         });
-    } else if let Some(event) = abi::pool::events::Swap::match_and_decode(log) {
+    } else if let Some(event) = protocol.decode_swap(log) {
         pool_sqrt_prices.push(events::PoolSqrtPrice {
             pool_address: pool_address.to_string(),
             ordinal: log.ordinal,
@@ -417,11 +438,13 @@ pub fn extract_transactions(
     transaction_trace: &TransactionTrace,
     timestamp_seconds: u64,
     block_number: u64,
+    protocol: Protocol,
 ) {
     let mut add_transaction = false;
     if abi::pool::events::Burn::match_log(log)
         || abi::pool::events::Mint::match_log(log)
-        || abi::pool::events::Swap::match_log(log)
+        || protocol.match_swap(log)
+        || abi::pool::events::Flash::match_log(log)
         || abi::positionmanager::events::IncreaseLiquidity::match_log(log)
         || abi::positionmanager::events::Collect::match_log(log)
         || abi::positionmanager::events::DecreaseLiquidity::match_log(log)
@@ -440,6 +463,152 @@ pub fn extract_transactions(
     }
 }
 
+/// Events of a Uniswap v4 pool, emitted by the `PoolManager` under the pool id. The swap amounts
+/// are turned into the ones of the pool, like the v3 ones, and the fee of a swap is the one it
+/// paid. The liquidity and fee growth are read from the pool state in the `PoolManager`.
+pub fn extract_v4_pool_events(
+    pool_sqrt_prices: &mut Vec<events::PoolSqrtPrice>,
+    pool_liquidities: &mut Vec<events::PoolLiquidity>,
+    fee_growth_updates: &mut Vec<events::FeeGrowthGlobal>,
+    pool_events: &mut Vec<events::PoolEvent>,
+    transaction_id: &String,
+    origin: &String,
+    log: &Log,
+    call: &Call,
+    pool: &Pool,
+    timestamp_seconds: u64,
+    block_number: u64,
+) {
+    let token0 = pool.token0.as_ref().unwrap();
+    let token1 = pool.token1.as_ref().unwrap();
+    let common_event = events::PoolEvent {
+        log_ordinal: log.ordinal,
+        log_index: log.block_index as u64,
+        pool_address: pool.address.to_string(),
+        token0: token0.address.clone(),
+        token1: token1.address.clone(),
+        fee: pool.fee_tier.clone(),
+        transaction_id: transaction_id.to_string(),
+        timestamp: timestamp_seconds,
+        created_at_block_number: block_number,
+        protocol: pool.protocol_name().to_string(),
+        ..Default::default()
+    };
+
+    if let Some(initialize) = v4::decode_initialize(&log.topics, &log.data) {
+        pool_sqrt_prices.push(events::PoolSqrtPrice {
+            pool_address: pool.address.to_string(),
+            ordinal: log.ordinal,
+            sqrt_price: initialize.sqrt_price_x96.to_string(),
+            tick: initialize.tick.to_string(),
+            initialized: true,
+        });
+        return;
+    }
+
+    if let Some(swap) = v4::decode_swap(&log.topics, &log.data) {
+        log::info!("V4 SWAP: transaction: {}", transaction_id.to_string());
+        if !pool.should_handle_swap() {
+            return;
+        }
+        pool_sqrt_prices.push(events::PoolSqrtPrice {
+            pool_address: pool.address.to_string(),
+            ordinal: log.ordinal,
+            sqrt_price: swap.sqrt_price_x96.to_string(),
+            tick: swap.tick.to_string(),
+            initialized: false,
+        });
+
+        // the event amounts are the balance deltas of the swapper
+        let amount0 = swap.amount0.neg().to_decimal(token0.decimals);
+        let amount1 = swap.amount1.neg().to_decimal(token1.decimals);
+        pool_events.push(events::PoolEvent {
+            fee: swap.fee.to_string(),
+            r#type: Some(SwapEvent(events::pool_event::Swap {
+                sender: Hex(&swap.sender).to_string(),
+                recipient: Hex(&swap.sender).to_string(),
+                origin: origin.to_string(),
+                amount_0: amount0.into(),
+                amount_1: amount1.into(),
+                sqrt_price: swap.sqrt_price_x96.into(),
+                liquidity: swap.liquidity.into(),
+                tick: swap.tick.into(),
+            })),
+            ..common_event
+        });
+    } else if let Some(modify) = v4::decode_modify_liquidity(&log.topics, &log.data) {
+        log::info!("V4 MODIFY LIQUIDITY: transaction: {}", transaction_id.to_string());
+        if !pool.should_handle_mint_and_burn() || modify.liquidity_delta.eq(&BigInt::zero()) {
+            return;
+        }
+
+        let (amount0, amount1) = match v4::decode_modify_liquidity_amounts(&call.return_data) {
+            Some(amounts) => amounts,
+            None => {
+                log::info!("no modifyLiquidity return data in transaction {}", transaction_id);
+                (BigInt::zero(), BigInt::zero())
+            }
+        };
+        let amount0 = amount0.to_decimal(token0.decimals);
+        let amount1 = amount1.to_decimal(token1.decimals);
+
+        let r#type = if modify.liquidity_delta.gt(&BigInt::zero()) {
+            MintEvent(events::pool_event::Mint {
+                owner: Hex(&modify.sender).to_string(),
+                sender: Hex(&modify.sender).to_string(),
+                origin: origin.to_string(),
+                amount: modify.liquidity_delta.to_string(),
+                amount_0: amount0.into(),
+                amount_1: amount1.into(),
+                tick_lower: modify.tick_lower.to_string(),
+                tick_upper: modify.tick_upper.to_string(),
+            })
+        } else {
+            BurnEvent(events::pool_event::Burn {
+                owner: Hex(&modify.sender).to_string(),
+                origin: origin.to_string(),
+                amount: modify.liquidity_delta.neg().to_string(),
+                amount_0: amount0.into(),
+                amount_1: amount1.into(),
+                tick_lower: modify.tick_lower.to_string(),
+                tick_upper: modify.tick_upper.to_string(),
+            })
+        };
+        pool_events.push(events::PoolEvent {
+            r#type: Some(r#type),
+            ..common_event
+        });
+    } else {
+        return;
+    }
+
+    let pool_id = hex::decode(&pool.address).unwrap();
+    let storage = PoolManagerStorage::new(&call.storage_changes, &log.address, &pool_id);
+    let liquidity = bigint_if_some(storage.liquidity());
+    if liquidity != "" {
+        pool_liquidities.push(events::PoolLiquidity {
+            pool_address: pool.address.to_string(),
+            liquidity,
+            token0: token0.address.clone(),
+            token1: token1.address.clone(),
+            log_ordinal: log.ordinal,
+        });
+    }
+    for (token_idx, fee_growth) in [storage.fee_growth_global0x128(), storage.fee_growth_global1x128()]
+        .into_iter()
+        .enumerate()
+    {
+        if let Some((_, new_value)) = fee_growth {
+            fee_growth_updates.push(events::FeeGrowthGlobal {
+                pool_address: pool.address.to_string(),
+                ordinal: log.ordinal,
+                token_idx: token_idx as i32,
+                new_value: new_value.into(),
+            });
+        }
+    }
+}
+
 fn extract_positions(
     pool: &Pool,
     increase_liquidity_positions: &mut Vec<events::IncreaseLiquidityPosition>,
@@ -447,9 +616,10 @@ fn extract_positions(
     collect_positions: &mut Vec<events::CollectPosition>,
     transfer_positions: &mut Vec<events::TransferPosition>,
     call: &Call,
+    position_manager: &[u8],
 ) {
     for log in call.logs.iter() {
-        if log.address != NON_FUNGIBLE_POSITION_MANAGER {
+        if log.address != position_manager {
             return;
         }
 
@@ -531,19 +701,38 @@ fn extract_positions(
     }
 }
 
-// pub fn extract_flashes(flashes: &mut Vec<events::Flash>, log: &Log) {
-//     if abi::pool::events::Flash::match_log(&log) {
-//         let pool_address: String = Hex(&log.address).to_string();
-//
-//         // FIXME: kill those `rpc` calls here!
-//         log::info!("pool_address: {}", pool_address);
-//         let (fee_growth_global_0x_128, fee_growth_global_1x_128) = rpc::fee_growth_global_x128_call(&pool_address);
-//
-//         flashes.push(events::Flash {
-//             pool_address,
-//             fee_growth_global_0x_128: fee_growth_global_0x_128.to_string(),
-//             fee_growth_global_1x_128: fee_growth_global_1x_128.to_string(),
-//             log_ordinal: log.ordinal,
-//         });
-//     }
-// }
+pub fn extract_flashes(
+    flashes: &mut Vec<events::Flash>,
+    log: &Log,
+    storage_changes: &Vec<StorageChange>,
+    pool: &Pool,
+    transaction_id: &String,
+    timestamp_seconds: u64,
+) {
+    if let Some(flash) = abi::pool::events::Flash::match_and_decode(log) {
+        log::info!("FLASH: transaction: {}", transaction_id.to_string());
+        let token0 = pool.token0.as_ref().unwrap();
+        let token1 = pool.token1.as_ref().unwrap();
+
+        // the paid fees are added to the fee growth of the pool, read from its storage like for the swaps
+        let storage = UniswapPoolStorage::with_layout(storage_changes, &log.address, pool.protocol().pool_layout());
+
+        flashes.push(events::Flash {
+            pool_address: pool.address.to_string(),
+            fee_growth_global_0x_128: bigint_if_some(storage.fee_growth_global0x128()),
+            fee_growth_global_1x_128: bigint_if_some(storage.fee_growth_global1x128()),
+            log_ordinal: log.ordinal,
+            sender: Hex(&flash.sender).to_string(),
+            recipient: Hex(&flash.recipient).to_string(),
+            token0: token0.address.clone(),
+            token1: token1.address.clone(),
+            amount_0: flash.amount0.to_decimal(token0.decimals).into(),
+            amount_1: flash.amount1.to_decimal(token1.decimals).into(),
+            paid_0: flash.paid0.to_decimal(token0.decimals).into(),
+            paid_1: flash.paid1.to_decimal(token1.decimals).into(),
+            transaction_id: transaction_id.to_string(),
+            timestamp: timestamp_seconds,
+            log_index: log.block_index as u64,
+        });
+    }
+}
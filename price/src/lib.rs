@@ -2,17 +2,25 @@ extern crate core;
 
 pub mod abi;
 mod ast;
+mod config;
 mod db;
 mod eth;
 mod filtering;
 mod math;
+mod outliers;
 mod pb;
 mod price;
+mod protocol;
 mod rpc;
+mod stablecoins;
 mod storage;
+mod tick_bitmap;
 mod ticks_idx;
 mod utils;
+mod v2;
+mod v4;
 
+use crate::config::Config;
 use crate::ethpb::v2::{Block, StorageChange};
 use crate::pb::uniswap;
 use crate::pb::uniswap::events::pool_event::Type;
@@ -22,10 +30,8 @@ use crate::pb::uniswap::events::position_event::Type::{
 };
 use crate::pb::uniswap::events::{PoolSqrtPrice, PositionEvent};
 use crate::pb::uniswap::{events, Events};
-use crate::pb::uniswap::{Erc20Token, Erc20Tokens, Pool, Pools};
-use crate::price::WHITELIST_TOKENS;
-use crate::utils::{ERROR_POOL, UNISWAP_V3_FACTORY};
-use std::ops::{Div, Mul, Sub};
+use crate::pb::uniswap::{Erc20Token, Erc20Tokens, Pool, Pools, RejectedPrice, RejectedPrices, ScreenedPrices};
+use std::ops::{Add, Div, Mul, Sub};
 use substreams::errors::Error;
 use substreams::key;
 use substreams::pb::substreams::{store_delta, Clock};
@@ -41,18 +47,20 @@ use substreams_entity_change::tables::Tables;
 use substreams_ethereum::{pb::eth as ethpb, Event as EventTrait};
 
 #[substreams::handlers::map]
-pub fn map_pools_created(block: Block) -> Result<Pools, Error> {
+pub fn map_pools_created(params: String, block: Block, v4_pools: Pools) -> Result<Pools, Error> {
     use abi::factory::events::PoolCreated;
+    let config = Config::parse(&params)?;
 
-    Ok(Pools {
+    let mut pools = Pools {
         pools: block
-            .events::<PoolCreated>(&[&UNISWAP_V3_FACTORY])
+            .events::<PoolCreated>(&config.factories())
             .filter_map(|(event, log)| {
                 log::info!("pool addr: {}", Hex(&event.pool));
 
-                if event.pool == ERROR_POOL {
+                if event.pool == config.error_pool {
                     return None;
                 }
+                let profile = config.profile_of_factory(log.address())?;
 
                 let token0_address = Hex(&event.token0).to_string();
                 let token1_address = Hex(&event.token1).to_string();
@@ -68,7 +76,8 @@ pub fn map_pools_created(block: Block) -> Result<Pools, Error> {
                     fee_tier: event.fee.to_string(),
                     tick_spacing: event.tick_spacing.into(),
                     log_ordinal: log.ordinal(),
-                    ignore_pool: event.pool == ERROR_POOL,
+                    ignore_pool: event.pool == config.error_pool,
+                    protocol: profile.protocol.name().to_string(),
                     token0: Some(match rpc::create_uniswap_token(&token0_address) {
                         Some(mut token) => {
                             token.total_supply = rpc::token_total_supply_call(&token0_address)
@@ -99,7 +108,12 @@ pub fn map_pools_created(block: Block) -> Result<Pools, Error> {
                 })
             })
             .collect(),
-    })
+    };
+
+    // Uniswap v4 pools are initialized in the PoolManager instead of being created by a factory
+    pools.pools.extend(v4_pools.pools);
+    pools.pools.sort_by_key(|pool| pool.log_ordinal);
+    Ok(pools)
 }
 
 #[substreams::handlers::store]
@@ -132,15 +146,16 @@ pub fn store_pool_count(pools: Pools, store: StoreAddBigInt) {
 }
 
 #[substreams::handlers::map]
-pub fn map_tokens_whitelist_pools(pools: Pools) -> Result<Erc20Tokens, Error> {
+pub fn map_tokens_whitelist_pools(params: String, pools: Pools) -> Result<Erc20Tokens, Error> {
+    let config = Config::parse(&params)?;
     let mut tokens = vec![];
 
     for pool in pools.pools {
         let mut token0 = pool.token0();
         let mut token1 = pool.token1();
 
-        let token0_whitelisted = WHITELIST_TOKENS.contains(&token0.address.as_str());
-        let token1_whitelisted = WHITELIST_TOKENS.contains(&token1.address.as_str());
+        let token0_whitelisted = config.is_whitelisted(&token0.address);
+        let token1_whitelisted = config.is_whitelisted(&token1.address);
 
         if token0_whitelisted {
             log::info!("adding pool: {} to token: {}", pool.address, token1.address);
@@ -166,7 +181,13 @@ pub fn store_tokens_whitelist_pools(tokens: Erc20Tokens, output_append: StoreApp
 }
 
 #[substreams::handlers::map]
-pub fn map_extract_data_types(block: Block, pools_store: StoreGetProto<Pool>) -> Result<Events, Error> {
+pub fn map_extract_data_types(
+    params: String,
+    block: Block,
+    pools_store: StoreGetProto<Pool>,
+    v4_events: Events,
+) -> Result<Events, Error> {
+    let config = Config::parse(&params)?;
     let mut events = Events::default();
 
     let mut pool_sqrt_prices: Vec<events::PoolSqrtPrice> = vec![];
@@ -174,7 +195,7 @@ pub fn map_extract_data_types(block: Block, pools_store: StoreGetProto<Pool>) ->
     let mut fee_growth_global_updates: Vec<events::FeeGrowthGlobal> = vec![];
     let mut pool_events: Vec<events::PoolEvent> = vec![];
     let mut transactions: Vec<events::Transaction> = vec![];
-    // let mut flashes: Vec<events::Flash> = vec![];
+    let mut flashes: Vec<events::Flash> = vec![];
     let mut ticks_created: Vec<events::TickCreated> = vec![];
     let mut ticks_updated: Vec<events::TickUpdated> = vec![];
 
@@ -196,7 +217,11 @@ pub fn map_extract_data_types(block: Block, pools_store: StoreGetProto<Pool>) ->
                 continue;
             }
             let pool = pool_opt.unwrap();
-            filtering::extract_pool_sqrt_prices(&mut pool_sqrt_prices, log, pool_address);
+            let profile = match config.profile(&pool.protocol) {
+                Some(profile) => profile,
+                None => continue,
+            };
+            filtering::extract_pool_sqrt_prices(&mut pool_sqrt_prices, log, pool_address, profile.protocol);
             filtering::extract_pool_liquidities(&mut pool_liquidities, log, &call_view.call.storage_changes, &pool);
             filtering::extract_fee_growth_update(
                 &mut fee_growth_global_updates,
@@ -221,11 +246,19 @@ pub fn map_extract_data_types(block: Block, pools_store: StoreGetProto<Pool>) ->
                 &pool,
                 timestamp,
                 block.number,
+                profile,
             );
 
-            filtering::extract_transactions(&mut transactions, log, &trx, timestamp, block.number);
+            filtering::extract_transactions(&mut transactions, log, &trx, timestamp, block.number, profile.protocol);
 
-            // filtering::extract_flashes(&mut flashes, &log);
+            filtering::extract_flashes(
+                &mut flashes,
+                log,
+                &call_view.call.storage_changes,
+                &pool,
+                &transactions_id,
+                timestamp,
+            );
         }
     }
 
@@ -239,13 +272,185 @@ pub fn map_extract_data_types(block: Block, pools_store: StoreGetProto<Pool>) ->
     events.decrease_liquidity_positions = positions_decrease_liquidity;
     events.collect_positions = positions_collect;
     events.transfer_positions = positions_transfer;
-    // events.flashes = flashes;
+    events.flashes = flashes;
     events.ticks_created = ticks_created;
     events.ticks_updated = ticks_updated;
+    events.merge_v4(v4_events);
+
+    Ok(events)
+}
+
+/// Count of the ticks recorded in the ring of `store_pool_ticks`, `pool:{address}:ticks`.
+#[substreams::handlers::store]
+pub fn store_pool_tick_counts(
+    params: String,
+    events: Events,
+    pool_liquidities_store: StoreGetBigInt, /* store_pool_liquidities */
+    store: StoreAddInt64,
+) {
+    let params = outliers::Params::parse(&params).unwrap();
+    for sqrt_price in events.pool_sqrt_prices {
+        if is_ring_tick(&params, &sqrt_price, &pool_liquidities_store) {
+            store.add(sqrt_price.ordinal, format!("pool:{}:ticks", sqrt_price.pool_address), 1);
+        }
+    }
+}
+
+/// Ring of the last `TICK_HISTORY` ticks of every pool, `pool:{address}:tick:{slot}`, the slot
+/// being the count of `store_pool_tick_counts`. Ticks below the liquidity floor of the outlier
+/// params are left out. Ticks further than `max_deviation_bps` from the median are still recorded:
+/// the ring is an input of `map_screened_prices` and cannot read its output back.
+#[substreams::handlers::store]
+pub fn store_pool_ticks(
+    params: String,
+    events: Events,
+    pool_tick_counts_store: StoreGetInt64,  /* store_pool_tick_counts */
+    pool_liquidities_store: StoreGetBigInt, /* store_pool_liquidities */
+    store: StoreSetInt64,
+) {
+    let params = outliers::Params::parse(&params).unwrap();
+    for sqrt_price in events.pool_sqrt_prices {
+        if !is_ring_tick(&params, &sqrt_price, &pool_liquidities_store) {
+            continue;
+        }
+        let pool_address = &sqrt_price.pool_address;
+        let count = pool_tick_counts_store
+            .get_at(sqrt_price.ordinal, format!("pool:{pool_address}:ticks"))
+            .unwrap_or(0);
+        let slot = count as u64 % outliers::TICK_HISTORY;
+        let tick = sqrt_price.tick.parse::<i64>().unwrap();
+        store.set(sqrt_price.ordinal, format!("pool:{pool_address}:tick:{slot}"), &tick);
+    }
+}
+
+/// Pool price recorded in the tick ring, the initial prices and the ones below the liquidity floor
+/// are left out.
+fn is_ring_tick(
+    params: &outliers::Params,
+    sqrt_price: &PoolSqrtPrice,
+    pool_liquidities_store: &StoreGetBigInt,
+) -> bool {
+    if sqrt_price.initialized {
+        return false;
+    }
+    let liquidity = pool_liquidities_store
+        .get_at(sqrt_price.ordinal, format!("pool:{}", sqrt_price.pool_address))
+        .unwrap_or(BigInt::zero());
+    !params.below_liquidity_floor(&liquidity)
+}
+
+/// Pool prices of `map_extract_data_types` split in the accepted ones and the ones rejected by the
+/// outlier params, the single place where outliers are decided.
+#[substreams::handlers::map]
+pub fn map_screened_prices(
+    params: String,
+    events: Events,
+    pool_ticks_store: StoreGetInt64,        /* store_pool_ticks */
+    pool_liquidities_store: StoreGetBigInt, /* store_pool_liquidities */
+) -> Result<ScreenedPrices, Error> {
+    let params = outliers::Params::parse(&params)?;
+    let (accepted_prices, rejected_prices) = reject_outliers(
+        &params,
+        events.pool_sqrt_prices,
+        &pool_ticks_store,
+        &pool_liquidities_store,
+    );
+    Ok(ScreenedPrices {
+        accepted_prices,
+        rejected_prices,
+    })
+}
 
+/// `map_extract_data_types` with the pool prices accepted by `map_screened_prices` only. Every
+/// other event is passed through.
+#[substreams::handlers::map]
+pub fn map_filtered_data_types(events: Events, screened_prices: ScreenedPrices) -> Result<Events, Error> {
+    let mut events = events;
+    let rejected = screened_prices.rejected_prices;
+    events.pool_sqrt_prices = screened_prices.accepted_prices;
+    // the swap that moved the pool to a rejected price is left out along with it
+    events.pool_events.retain(|event| {
+        !matches!(event.r#type, Some(SwapEvent(_)))
+            || !rejected
+                .iter()
+                .any(|price| price.ordinal == event.log_ordinal && price.pool_address == event.pool_address)
+    });
     Ok(events)
 }
 
+/// Pool prices left out of `map_filtered_data_types` and why.
+#[substreams::handlers::map]
+pub fn map_rejected_prices(screened_prices: ScreenedPrices) -> Result<RejectedPrices, Error> {
+    Ok(RejectedPrices {
+        rejected_prices: screened_prices.rejected_prices,
+    })
+}
+
+fn reject_outliers(
+    params: &outliers::Params,
+    sqrt_prices: Vec<PoolSqrtPrice>,
+    pool_ticks_store: &StoreGetInt64,
+    pool_liquidities_store: &StoreGetBigInt,
+) -> (Vec<PoolSqrtPrice>, Vec<RejectedPrice>) {
+    if !params.enabled() {
+        return (sqrt_prices, vec![]);
+    }
+    let mut accepted = vec![];
+    let mut rejected = vec![];
+    for sqrt_price in sqrt_prices {
+        // the initial price of a pool has nothing to be compared with
+        if sqrt_price.initialized {
+            accepted.push(sqrt_price);
+            continue;
+        }
+        let pool_address = &sqrt_price.pool_address;
+        let ord = sqrt_price.ordinal;
+        let liquidity = pool_liquidities_store
+            .get_at(ord, format!("pool:{pool_address}"))
+            .unwrap_or(BigInt::zero());
+        let tick = sqrt_price.tick.parse::<i32>().unwrap();
+        // ticks before this price, its own one is set at the same ordinal
+        let mut ticks: Vec<i32> = (0..outliers::TICK_HISTORY)
+            .filter_map(|slot| {
+                pool_ticks_store.get_at(ord.saturating_sub(1), format!("pool:{pool_address}:tick:{slot}"))
+            })
+            .map(|tick| tick as i32)
+            .collect();
+        let samples = ticks.len();
+        let median = outliers::median_tick(&mut ticks);
+        let deviation_bps = median.map_or(0.0, |median| outliers::tick_deviation_bps(tick, median));
+
+        let reason = if params.below_liquidity_floor(&liquidity) {
+            "liquidity below floor"
+        } else if params.max_deviation_bps > 0
+            && samples >= params.min_samples
+            && deviation_bps > params.max_deviation_bps as f64
+        {
+            "deviation from rolling median"
+        } else {
+            accepted.push(sqrt_price);
+            continue;
+        };
+        log::info!(
+            "rejecting price of pool {} at ordinal {}: {}",
+            pool_address,
+            ord,
+            reason
+        );
+        rejected.push(RejectedPrice {
+            pool_address: pool_address.clone(),
+            ordinal: ord,
+            sqrt_price: sqrt_price.sqrt_price,
+            tick: sqrt_price.tick,
+            median_tick: median.map(|median| median.to_string()).unwrap_or_default(),
+            deviation_bps,
+            liquidity: liquidity.to_string(),
+            reason: reason.to_string(),
+        });
+    }
+    (accepted, rejected)
+}
+
 #[substreams::handlers::store]
 pub fn store_pool_sqrt_price(events: Events, store: StoreSetProto<PoolSqrtPrice>) {
     for sqrt_price in events.pool_sqrt_prices {
@@ -301,7 +506,7 @@ pub fn store_prices(clock: Clock, events: Events, pools_store: StoreGetProto<Poo
                     sqrt_price_update.ordinal,
                     &vec![
                         format!("pool:{pool_address}:{token0_addr}:token0"),
-                        format!("pair:{token0_addr}:{token1_addr}"), // used for find_eth_per_token
+                        format!("pair:{token0_addr}:{token1_addr}"), // used for find_eth_per_token_and_source
                     ],
                     &tokens_price.0,
                 );
@@ -310,7 +515,7 @@ pub fn store_prices(clock: Clock, events: Events, pools_store: StoreGetProto<Poo
                     sqrt_price_update.ordinal,
                     &vec![
                         format!("pool:{pool_address}:{token1_addr}:token1"),
-                        format!("pair:{token1_addr}:{token0_addr}"), // used for find_eth_per_token
+                        format!("pair:{token1_addr}:{token0_addr}"), // used for find_eth_per_token_and_source
                     ],
                     &tokens_price.1,
                 );
@@ -374,13 +579,14 @@ pub fn store_pool_liquidities(clock: Clock, events: Events, store: StoreSetBigIn
 }
 
 #[substreams::handlers::store]
-pub fn store_total_tx_counts(clock: Clock, events: Events, output: StoreAddBigInt) {
+pub fn store_total_tx_counts(params: String, clock: Clock, events: Events, output: StoreAddBigInt) {
+    let config = Config::parse(&params).unwrap();
     let timestamp_seconds = clock.timestamp.unwrap().seconds;
     let day_id = timestamp_seconds / 86400;
     let hour_id = timestamp_seconds / 3600;
     let prev_day_id = day_id - 1;
     let prev_hour_id = hour_id - 1;
-    let factory_addr = Hex(UNISWAP_V3_FACTORY);
+    let factory_addr = Hex(&config.factory);
 
     output.delete_prefix(0, &format!("UniswapDayData:{prev_day_id}:"));
     output.delete_prefix(0, &format!("PoolDayData:{prev_day_id}:"));
@@ -415,13 +621,17 @@ pub fn store_total_tx_counts(clock: Clock, events: Events, output: StoreAddBigIn
 
 #[substreams::handlers::store]
 pub fn store_swaps_volume(
+    params: String,
     clock: Clock,
     events: Events,
     store_pool: StoreGetProto<Pool>,
     store_total_tx_counts: StoreGetBigInt,
     store_eth_prices: StoreGetBigDecimal,
+    store_ticks_liquidities: StoreGetBigInt,
+    store_tick_words: StoreGetInt64,
     output: StoreAddBigDecimal,
 ) {
+    let config = Config::parse(&params).unwrap();
     let timestamp_seconds = clock.timestamp.unwrap().seconds;
     let day_id = timestamp_seconds / 86400;
     let hour_id = timestamp_seconds / 3600;
@@ -433,6 +643,8 @@ pub fn store_swaps_volume(
     output.delete_prefix(0, &format!("PoolHourData:{prev_hour_id}:"));
     output.delete_prefix(0, &format!("TokenDayData:{prev_day_id}:"));
     output.delete_prefix(0, &format!("TokenHourData:{prev_hour_id}:"));
+    output.delete_prefix(0, &format!("TickDayData:{prev_day_id}:"));
+    output.delete_prefix(0, &format!("TickHourData:{prev_hour_id}:"));
 
     for event in events.pool_events {
         let ord = event.log_ordinal;
@@ -452,6 +664,14 @@ pub fn store_swaps_volume(
             ),
             SwapEvent(swap) => {
                 log::info!("transaction: {}", pool.transaction_id);
+                output.add_many(
+                    ord,
+                    &vec![
+                        format!("PoolDayData:{day_id}:{pool_address}:swapCount"),
+                        format!("PoolHourData:{hour_id}:{pool_address}:swapCount"),
+                    ],
+                    &BigDecimal::one(),
+                );
                 let eth_price_in_usd: BigDecimal = match store_eth_prices.get_at(ord, "bundle") {
                     None => {
                         panic!("bundle eth price not found")
@@ -488,6 +708,7 @@ pub fn store_swaps_volume(
                     &token0_derived_eth_price,
                     &token1_derived_eth_price,
                     &eth_price_in_usd,
+                    &config,
                 );
 
                 log::info!("volumeAmounts.eth {}", volume_amounts.delta_tvl_eth);
@@ -502,7 +723,8 @@ pub fn store_swaps_volume(
                     .clone()
                     .div(BigDecimal::from(2 as i32));
 
-                let fee_tier = BigDecimal::try_from(pool.fee_tier).unwrap();
+                // fee applied by the swap, set by the hooks of the v4 pools with a dynamic fee
+                let fee_tier = BigDecimal::try_from(event.fee.as_str()).unwrap();
                 let fee_eth: BigDecimal = volume_eth
                     .clone()
                     .mul(fee_tier.clone())
@@ -603,10 +825,106 @@ pub fn store_swaps_volume(
                     &fee_usd,
                 );
                 output.add(ord, format!("factory:totalFeesETH"), &fee_eth);
+
+                // the swap is in the liquidity range starting at the nearest initialized tick at or
+                // below the pool tick, the tick windows point to initialized ticks only
+                if pool.tick_spacing > 0 {
+                    let tick_idx = tick_bitmap::nearest_initialized_tick(
+                        swap.tick.parse::<i32>().unwrap(),
+                        pool.tick_spacing,
+                        |word| {
+                            store_tick_words
+                                .get_at(ord, format!("word:{pool_address}:{word}"))
+                                .map_or(false, |count| count > 0)
+                        },
+                        |tick_idx| {
+                            store_ticks_liquidities
+                                .get_at(ord, format!("tick:{pool_address}:{tick_idx}:liquidityGross"))
+                                .map_or(false, |liquidity_gross| liquidity_gross.gt(&BigInt::zero()))
+                        },
+                    );
+                    if let Some(tick_idx) = tick_idx {
+                        let windows = [
+                            format!("TickDayData:{day_id}:{pool_address}:{tick_idx}"),
+                            format!("TickHourData:{hour_id}:{pool_address}:{tick_idx}"),
+                        ];
+                        for window in windows {
+                            output.add(ord, format!("{window}:volumeToken0"), &amount0_abs);
+                            output.add(ord, format!("{window}:volumeToken1"), &amount1_abs);
+                            output.add(ord, format!("{window}:volumeUSD"), &volume_usd);
+                            output.add(ord, format!("{window}:feesUSD"), &fee_usd);
+                        }
+                    }
+                }
             }
             _ => {}
         }
     }
+
+    // the fees paid by the flashes are collected by the pool like the ones of the swaps
+    for flash in events.flashes {
+        let ord = flash.log_ordinal;
+        let pool_address = &flash.pool_address;
+        if !store_total_tx_counts.has_last(format!("pool:{pool_address}")) {
+            continue;
+        }
+
+        let token0_addr = &flash.token0;
+        let token1_addr = &flash.token1;
+        let eth_price_in_usd: BigDecimal = match store_eth_prices.get_at(ord, "bundle") {
+            None => {
+                panic!("bundle eth price not found")
+            }
+            Some(price) => price,
+        };
+        let token0_derived_eth_price = match store_eth_prices.get_at(ord, format!("token:{token0_addr}:dprice:eth")) {
+            None => continue,
+            Some(price) => price,
+        };
+        let token1_derived_eth_price = match store_eth_prices.get_at(ord, format!("token:{token1_addr}:dprice:eth")) {
+            None => continue,
+            Some(price) => price,
+        };
+
+        let paid0 = BigDecimal::try_from(flash.paid_0.as_str()).unwrap();
+        let paid1 = BigDecimal::try_from(flash.paid_1.as_str()).unwrap();
+        let fee_eth = paid0
+            .clone()
+            .mul(token0_derived_eth_price)
+            .add(paid1.clone().mul(token1_derived_eth_price));
+        let fee_usd = fee_eth.clone().mul(eth_price_in_usd);
+
+        log::info!("flash fee_eth {}", fee_eth);
+        log::info!("flash fee_usd {}", fee_usd);
+
+        output.add(ord, format!("pool:{pool_address}:collectedFeesToken0"), &paid0);
+        output.add(ord, format!("pool:{pool_address}:collectedFeesToken1"), &paid1);
+        output.add(ord, format!("pool:{pool_address}:collectedFeesUSD"), &fee_usd);
+
+        let mut fee_usd_keys = vec![
+            format!("pool:{pool_address}:feesUSD"),
+            format!("token:{token0_addr}:feesUSD"),
+            format!("token:{token1_addr}:feesUSD"),
+            format!("factory:totalFeesUSD"),
+        ];
+        // a flash is not counted as a pool transaction, so it only adds to the windows that the
+        // swaps, mints and burns of the pool already opened
+        for window in [
+            format!("UniswapDayData:{day_id}"),
+            format!("PoolDayData:{day_id}:{pool_address}"),
+            format!("PoolHourData:{hour_id}:{pool_address}"),
+            format!("TokenDayData:{day_id}:{token0_addr}"),
+            format!("TokenDayData:{day_id}:{token1_addr}"),
+            format!("TokenHourData:{hour_id}:{token0_addr}"),
+            format!("TokenHourData:{hour_id}:{token1_addr}"),
+        ] {
+            if store_total_tx_counts.has_last(&window) {
+                fee_usd_keys.push(format!("{window}:feesUSD"));
+            }
+        }
+        output.add_many(ord, &fee_usd_keys, &fee_usd);
+        output.add(ord, format!("factory:totalFeesETH"), &fee_eth);
+    }
 }
 
 /**
@@ -649,17 +967,101 @@ pub fn store_native_amounts(events: Events, store: StoreAddBigDecimal) {
     }
 }
 
+/// Market price of every stablecoin, read from its pool with WETH, and its ratio to the median
+/// price of the other stablecoins. The prices are rescaled to dollars by the stablecoins still
+/// pegged while `usd_token` is depegged. `depegged:{address}` is `1` while the ratio is outside of the
+/// `band_bps` param, `find_eth_per_token_and_source` then stops treating the stablecoin as worth one dollar.
+#[substreams::handlers::store]
+pub fn store_stablecoin_pegs(
+    params: String,
+    clock: Clock,
+    events: Events,                   /* map_filtered_data_types */
+    pools_store: StoreGetProto<Pool>, /* store_pools_created */
+    prices_store: StoreGetBigDecimal, /* store_prices */
+    output: StoreSetBigDecimal,
+) {
+    let config = Config::parse_with(&params, &stablecoins::KEYS).unwrap();
+    let params = stablecoins::Params::parse(&params).unwrap();
+    let hour_id = clock.timestamp.unwrap().seconds / 3600;
+    let prev_hour_id = hour_id - 1;
+
+    output.delete_prefix(0, &format!("StablecoinHourData:{prev_hour_id}:"));
+
+    for pool_sqrt_price in events.pool_sqrt_prices {
+        let ord = pool_sqrt_price.ordinal;
+        let pool = match pools_store.get_last(format!("pool:{}", pool_sqrt_price.pool_address)) {
+            Some(pool) => pool,
+            None => continue,
+        };
+        // Pegs only move with the pools of stablecoins, USDC/WETH included
+        let token0_addr = pool.token0_ref().address().as_str();
+        let token1_addr = pool.token1_ref().address().as_str();
+        if !config.is_stable_coin(token0_addr) && !config.is_stable_coin(token1_addr) {
+            continue;
+        }
+
+        let eth_price_usd = price::get_eth_price_in_usd(&prices_store, ord, &config);
+        let stablecoin_prices: Vec<(String, BigDecimal)> = config
+            .stable_coins
+            .iter()
+            .filter_map(|stablecoin| {
+                let eth_price = prices_store.get_at(ord, format!("pair:{}:{stablecoin}", config.weth))?;
+                Some((stablecoin.to_string(), eth_price.mul(eth_price_usd.clone())))
+            })
+            .collect();
+
+        // the prices are in `usd_token`, rescaled to dollars while it is depegged
+        let peg_ratios = stablecoins::peg_ratios(&stablecoin_prices);
+        let usd_token_scale =
+            stablecoins::usd_token_scale(&stablecoin_prices, &peg_ratios, &config.usd_token, params.band_bps);
+        for (stablecoin, peg_ratio) in peg_ratios {
+            let price_usd = stablecoin_prices
+                .iter()
+                .find(|(address, _)| address == &stablecoin)
+                .unwrap()
+                .1
+                .clone()
+                .div(usd_token_scale.clone());
+            let depegged = match stablecoins::is_depegged(&peg_ratio, params.band_bps) {
+                true => BigDecimal::one(),
+                false => BigDecimal::zero(),
+            };
+            output.set(
+                ord,
+                format!("StablecoinHourData:{hour_id}:{stablecoin}:price"),
+                &price_usd,
+            );
+            output.set(
+                ord,
+                format!("StablecoinHourData:{hour_id}:{stablecoin}:peg"),
+                &peg_ratio,
+            );
+            output.set_many(
+                ord,
+                &vec![
+                    format!("depegged:{stablecoin}"),
+                    format!("StablecoinHourData:{hour_id}:{stablecoin}:depegged"),
+                ],
+                &depegged,
+            );
+        }
+    }
+}
+
 #[substreams::handlers::store]
 pub fn store_eth_prices(
+    params: String,
     clock: Clock,
-    events: Events,                                /* map_extract_data_types */
+    events: Events,                                /* map_filtered_data_types */
     pools_store: StoreGetProto<Pool>,              /* store_pools_created */
     prices_store: StoreGetBigDecimal,              /* store_prices */
     tokens_whitelist_pools_store: StoreGetRaw,     /* store_tokens_whitelist_pools */
     total_native_amount_store: StoreGetBigDecimal, /* store_native_amounts */
     pool_liquidities_store: StoreGetBigInt,        /* store_pool_liquidities */
+    stablecoin_pegs_store: StoreGetBigDecimal,     /* store_stablecoin_pegs */
     output: StoreSetBigDecimal,
 ) {
+    let config = Config::parse(&params).unwrap();
     let timestamp_seconds = clock.timestamp.unwrap().seconds;
     let day_id = timestamp_seconds / 86400;
     let hour_id = timestamp_seconds / 3600;
@@ -668,6 +1070,10 @@ pub fn store_eth_prices(
 
     output.delete_prefix(0, &format!("TokenDayData:{prev_day_id}:"));
     output.delete_prefix(0, &format!("TokenHourData:{prev_hour_id}:"));
+    output.delete_prefix(0, &format!("TokenDayDataETH:{prev_day_id}:"));
+    output.delete_prefix(0, &format!("TokenHourDataETH:{prev_hour_id}:"));
+    output.delete_prefix(0, &format!("TokenDayDataSource:{prev_day_id}:"));
+    output.delete_prefix(0, &format!("TokenHourDataSource:{prev_hour_id}:"));
 
     for pool_sqrt_price in events.pool_sqrt_prices {
         let ord = pool_sqrt_price.ordinal;
@@ -686,10 +1092,11 @@ pub fn store_eth_prices(
         token0.log();
         token1.log();
 
-        let bundle_eth_price_usd = price::get_eth_price_in_usd(&prices_store, ord);
+        let bundle_eth_price_usd =
+            price::get_pegged_eth_price_in_usd(&prices_store, &stablecoin_pegs_store, ord, &config);
         log::info!("bundle_eth_price_usd: {}", bundle_eth_price_usd);
 
-        let token0_derived_eth_price: BigDecimal = price::find_eth_per_token(
+        let (token0_derived_eth_price, token0_source_pool) = price::find_eth_per_token_and_source(
             ord,
             &pool.address,
             token0_addr,
@@ -698,12 +1105,14 @@ pub fn store_eth_prices(
             &tokens_whitelist_pools_store,
             &total_native_amount_store,
             &prices_store,
+            &stablecoin_pegs_store,
+            &config,
         );
         log::info!(format!(
             "token 0 {token0_addr} derived eth price: {token0_derived_eth_price}"
         ));
 
-        let token1_derived_eth_price: BigDecimal = price::find_eth_per_token(
+        let (token1_derived_eth_price, token1_source_pool) = price::find_eth_per_token_and_source(
             ord,
             &pool.address,
             token1_addr,
@@ -712,6 +1121,8 @@ pub fn store_eth_prices(
             &tokens_whitelist_pools_store,
             &total_native_amount_store,
             &prices_store,
+            &stablecoin_pegs_store,
+            &config,
         );
         log::info!(format!(
             "token 1 {token1_addr} derived eth price: {token1_derived_eth_price}"
@@ -757,7 +1168,378 @@ pub fn store_eth_prices(
             ],
             &token1_price_usd,
         );
+        // Same windows priced in ETH, kept under their own prefix so the USD windows stay untouched
+        output.set_many(
+            ord,
+            &vec![
+                format!("TokenDayDataETH:{day_id}:{token0_addr}"),
+                format!("TokenHourDataETH:{hour_id}:{token0_addr}"),
+            ],
+            &token0_derived_eth_price,
+        );
+        output.set_many(
+            ord,
+            &vec![
+                format!("TokenDayDataETH:{day_id}:{token1_addr}"),
+                format!("TokenHourDataETH:{hour_id}:{token1_addr}"),
+            ],
+            &token1_derived_eth_price,
+        );
+        // The pool each window price was read from ends the key, `store_price_sources` projects it
+        output.set_many(
+            ord,
+            &vec![
+                format!("TokenDayDataSource:{day_id}:{token0_addr}:{token0_source_pool}"),
+                format!("TokenHourDataSource:{hour_id}:{token0_addr}:{token0_source_pool}"),
+            ],
+            &token0_price_usd,
+        );
+        output.set_many(
+            ord,
+            &vec![
+                format!("TokenDayDataSource:{day_id}:{token1_addr}:{token1_source_pool}"),
+                format!("TokenHourDataSource:{hour_id}:{token1_addr}:{token1_source_pool}"),
+            ],
+            &token1_price_usd,
+        );
+    }
+}
+
+#[substreams::handlers::store]
+pub fn store_price_sources(
+    clock: Clock,
+    eth_prices_deltas: Deltas<DeltaBigDecimal>, /* store_eth_prices */
+    output: StoreSetString,
+) {
+    let timestamp_seconds = clock.timestamp.unwrap().seconds;
+    let day_id = timestamp_seconds / 86400;
+    let hour_id = timestamp_seconds / 3600;
+    let prev_day_id = day_id - 1;
+    let prev_hour_id = hour_id - 1;
+
+    output.delete_prefix(0, &format!("TokenDayData:{prev_day_id}:"));
+    output.delete_prefix(0, &format!("TokenHourData:{prev_hour_id}:"));
+
+    for delta in eth_prices_deltas
+        .iter()
+        .key_first_segment_in(["TokenDayDataSource", "TokenHourDataSource"])
+        .operation_not_eq(store_delta::Operation::Delete)
+    {
+        let table_name = key::first_segment(&delta.key).trim_end_matches("Source");
+        let time_id = key::segment_at(&delta.key, 1);
+        let token_addr = key::segment_at(&delta.key, 2);
+        output.set(
+            delta.ordinal,
+            format!("{table_name}:{time_id}:{token_addr}"),
+            &key::last_segment(&delta.key).to_string(),
+        );
+    }
+}
+
+#[substreams::handlers::map]
+pub fn map_v2_pairs_created(params: String, block: Block) -> Result<Pools, Error> {
+    let config = Config::parse(&params)?;
+    let mut pools = vec![];
+    for trx in block.transactions() {
+        for (log, _) in trx.logs_with_calls() {
+            if log.address != config.v2_factory {
+                continue;
+            }
+            let pair_created = match v2::decode_pair_created(&log.topics, &log.data) {
+                Some(pair_created) => pair_created,
+                None => continue,
+            };
+            // Pairs with a token that is not an ERC20 are discarded, same as the v3 pools
+            let token0 = match rpc::create_uniswap_token(&Hex(&pair_created.token0).to_string()) {
+                Some(token) => token,
+                None => continue,
+            };
+            let token1 = match rpc::create_uniswap_token(&Hex(&pair_created.token1).to_string()) {
+                Some(token) => token,
+                None => continue,
+            };
+            pools.push(Pool {
+                address: Hex(&pair_created.pair).to_string(),
+                transaction_id: Hex(&trx.hash).to_string(),
+                created_at_block_number: block.number,
+                created_at_timestamp: block.timestamp_seconds(),
+                // every v2 pair charges 0.3%
+                fee_tier: "3000".to_string(),
+                log_ordinal: log.ordinal,
+                protocol: "uniswap_v2".to_string(),
+                token0: Some(token0),
+                token1: Some(token1),
+                ..Default::default()
+            });
+        }
+    }
+    Ok(Pools { pools })
+}
+
+#[substreams::handlers::store]
+pub fn store_v2_pairs_created(pools: Pools, store: StoreSetProto<Pool>) {
+    for pool in pools.pools {
+        let pool_address = &pool.address;
+        store.set(pool.log_ordinal, format!("pair:{pool_address}"), &pool);
+    }
+}
+
+/// Pools of the Uniswap v4 `PoolManager`, from its `Initialize` events, merged into `map_pools_created`.
+/// They are keyed by pool id instead of address and carry their hooks. The fee tier of the pools with
+/// a dynamic fee is 0, the fee of each swap being in its event. The native ether is priced as WETH, the
+/// pools trading it have WETH as their token.
+#[substreams::handlers::map]
+pub fn map_v4_pools_created(params: String, block: Block) -> Result<Pools, Error> {
+    let config = Config::parse(&params)?;
+    let mut pools = vec![];
+    if config.pool_manager.is_empty() {
+        return Ok(Pools { pools });
+    }
+
+    for trx in block.transactions() {
+        for (log, _) in trx.logs_with_calls() {
+            if log.address != config.pool_manager {
+                continue;
+            }
+            let initialize = match v4::decode_initialize(&log.topics, &log.data) {
+                Some(initialize) => initialize,
+                None => continue,
+            };
+            // Pools with a currency that is not an ERC20 are discarded, same as the v3 pools
+            let token0 = match create_v4_token(&initialize.currency0, &config) {
+                Some(token) => token,
+                None => continue,
+            };
+            let token1 = match create_v4_token(&initialize.currency1, &config) {
+                Some(token) => token,
+                None => continue,
+            };
+            let hooks = match v4::is_zero_address(&initialize.hooks) {
+                true => String::new(),
+                false => Hex(&initialize.hooks).to_string(),
+            };
+            pools.push(Pool {
+                address: Hex(&initialize.id).to_string(),
+                transaction_id: Hex(&trx.hash).to_string(),
+                created_at_block_number: block.number,
+                created_at_timestamp: block.timestamp_seconds(),
+                fee_tier: match initialize.dynamic_fee() {
+                    true => "0".to_string(),
+                    false => initialize.fee.to_string(),
+                },
+                tick_spacing: initialize.tick_spacing.into(),
+                log_ordinal: log.ordinal,
+                protocol: "uniswap_v4".to_string(),
+                hooks,
+                dynamic_fee: initialize.dynamic_fee(),
+                token0: Some(token0),
+                token1: Some(token1),
+                ..Default::default()
+            });
+        }
+    }
+    Ok(Pools { pools })
+}
+
+fn create_v4_token(currency: &[u8], config: &Config) -> Option<Erc20Token> {
+    let token_address = match v4::is_zero_address(currency) {
+        true => config.weth.clone(),
+        false => Hex(currency).to_string(),
+    };
+    let mut token = rpc::create_uniswap_token(&token_address)?;
+    token.total_supply = rpc::token_total_supply_call(&token_address)
+        .unwrap_or(BigInt::zero())
+        .to_string();
+    Some(token)
+}
+
+/// Events of the Uniswap v4 pools, in the shape of the v3 ones and merged into
+/// `map_extract_data_types`. The `PoolManager` emits them under the pool id.
+#[substreams::handlers::map]
+pub fn map_v4_extract_data_types(
+    params: String,
+    block: Block,
+    pools_store: StoreGetProto<Pool>,
+) -> Result<Events, Error> {
+    let config = Config::parse(&params)?;
+    let mut events = Events::default();
+    if config.pool_manager.is_empty() {
+        return Ok(events);
+    }
+
+    let timestamp = block.timestamp_seconds();
+    for trx in block.transactions() {
+        for (log, call_view) in trx.logs_with_calls() {
+            if log.address != config.pool_manager {
+                continue;
+            }
+            let pool_id = match log.topics.get(1) {
+                Some(pool_id) => Hex(pool_id).to_string(),
+                None => continue,
+            };
+            let pool = match pools_store.get_last(format!("pool:{pool_id}")) {
+                Some(pool) => pool,
+                None => continue,
+            };
+
+            let pool_events_count = events.pool_events.len();
+            filtering::extract_v4_pool_events(
+                &mut events.pool_sqrt_prices,
+                &mut events.pool_liquidities,
+                &mut events.fee_growth_global_updates,
+                &mut events.pool_events,
+                &Hex(&trx.hash).to_string(),
+                &Hex(&trx.from).to_string(),
+                log,
+                call_view.call,
+                &pool,
+                timestamp,
+                block.number,
+            );
+            if events.pool_events.len() > pool_events_count {
+                events
+                    .transactions
+                    .push(utils::load_transaction(block.number, timestamp, log.ordinal, trx));
+            }
+        }
     }
+    Ok(events)
+}
+
+/// Accumulates, per token and source, the USD volume of its trades and the volume weighted sum of
+/// their prices. The volume weighted price of a window is `priceVolumeUSD / volumeUSD`.
+///
+/// Each side of a trade is priced at its execution price, the ratio of the swapped amounts, times
+/// the USD price of the other token. The v3-style swaps are read from `map_filtered_data_types`,
+/// without the ones whose pool price was rejected as an outlier, under the `protocol` of their pool.
+/// Uniswap v2 swaps are read from the block, a v2 pair against WETH or a stablecoin prices its other
+/// token even when that one has no v3 pool.
+#[substreams::handlers::store]
+pub fn store_dex_prices(
+    params: String,
+    clock: Clock,
+    block: Block,
+    events: Events,                       /* map_filtered_data_types */
+    v2_pairs_store: StoreGetProto<Pool>,  /* store_v2_pairs_created */
+    eth_prices_store: StoreGetBigDecimal, /* store_eth_prices */
+    output: StoreAddBigDecimal,
+) {
+    let config = Config::parse(&params).unwrap();
+    let timestamp_seconds = clock.timestamp.unwrap().seconds;
+    let day_id = timestamp_seconds / 86400;
+    let hour_id = timestamp_seconds / 3600;
+    let prev_day_id = day_id - 1;
+    let prev_hour_id = hour_id - 1;
+
+    output.delete_prefix(0, &format!("TokenDayData:{prev_day_id}:"));
+    output.delete_prefix(0, &format!("TokenHourData:{prev_hour_id}:"));
+
+    let add_trade = |ord: u64, token_addr: &String, source: &str, price_usd: BigDecimal, amount: BigDecimal| {
+        let volume_usd = amount.mul(price_usd.clone());
+        if volume_usd.eq(&BigDecimal::zero()) {
+            return;
+        }
+        output.add_many(
+            ord,
+            &vec![
+                format!("TokenDayData:{day_id}:{token_addr}:{source}:volumeUSD"),
+                format!("TokenHourData:{hour_id}:{token_addr}:{source}:volumeUSD"),
+            ],
+            &volume_usd,
+        );
+        output.add_many(
+            ord,
+            &vec![
+                format!("TokenDayData:{day_id}:{token_addr}:{source}:priceVolumeUSD"),
+                format!("TokenHourData:{hour_id}:{token_addr}:{source}:priceVolumeUSD"),
+            ],
+            &price_usd.mul(volume_usd),
+        );
+    };
+    let add_swap =
+        |ord: u64, source: &str, token0: &String, token1: &String, amount0: BigDecimal, amount1: BigDecimal| {
+            let amount0 = amount0.absolute();
+            let amount1 = amount1.absolute();
+            if amount0.eq(&BigDecimal::zero()) || amount1.eq(&BigDecimal::zero()) {
+                return;
+            }
+            if let Some(token1_price_usd) = token_price_usd(ord, token1, &eth_prices_store, &config) {
+                let price_usd = amount1.clone().div(amount0.clone()).mul(token1_price_usd);
+                add_trade(ord, token0, source, price_usd, amount0.clone());
+            }
+            if let Some(token0_price_usd) = token_price_usd(ord, token0, &eth_prices_store, &config) {
+                let price_usd = amount0.div(amount1.clone()).mul(token0_price_usd);
+                add_trade(ord, token1, source, price_usd, amount1);
+            }
+        };
+
+    for event in events.pool_events {
+        if let Some(SwapEvent(swap)) = event.r#type {
+            let source = match event.protocol.as_str() {
+                "" => "uniswap_v3",
+                protocol => protocol,
+            };
+            add_swap(
+                event.log_ordinal,
+                source,
+                &event.token0,
+                &event.token1,
+                BigDecimal::try_from(swap.amount_0).unwrap(),
+                BigDecimal::try_from(swap.amount_1).unwrap(),
+            );
+        }
+    }
+
+    for trx in block.transactions() {
+        for (log, _) in trx.logs_with_calls() {
+            let swap = match v2::decode_swap(&log.topics, &log.data) {
+                Some(swap) => swap,
+                None => continue,
+            };
+            let pair = match v2_pairs_store.get_last(format!("pair:{}", Hex(&log.address))) {
+                Some(pair) => pair,
+                None => continue,
+            };
+            let token0 = pair.token0.unwrap();
+            let token1 = pair.token1.unwrap();
+            add_swap(
+                log.ordinal,
+                "uniswap_v2",
+                &token0.address,
+                &token1.address,
+                (swap.amount0_in + swap.amount0_out).to_decimal(token0.decimals),
+                (swap.amount1_in + swap.amount1_out).to_decimal(token1.decimals),
+            );
+        }
+    }
+}
+
+/// USD price of a token derived from the Uniswap v3 pools. Without one, WETH is worth the ETH
+/// price and the stablecoins one dollar, `None` for the other tokens.
+fn token_price_usd(
+    ord: u64,
+    token_addr: &String,
+    eth_prices_store: &StoreGetBigDecimal,
+    config: &Config,
+) -> Option<BigDecimal> {
+    let eth_price_usd = eth_prices_store
+        .get_at(ord, "bundle")
+        .filter(|price| price.ne(&BigDecimal::zero()));
+    let derived_price_usd = eth_prices_store
+        .get_at(ord, format!("token:{token_addr}:dprice:eth"))
+        .zip(eth_price_usd.clone())
+        .map(|(derived_eth_price, eth_price_usd)| derived_eth_price.mul(eth_price_usd))
+        .filter(|price| price.ne(&BigDecimal::zero()));
+    if derived_price_usd.is_some() {
+        return derived_price_usd;
+    }
+    if token_addr.eq(&config.weth) {
+        return eth_price_usd;
+    }
+    if config.is_stable_coin(token_addr) {
+        return Some(BigDecimal::one());
+    }
+    None
 }
 
 #[substreams::handlers::store]
@@ -791,6 +1573,7 @@ pub fn store_token_tvl(events: Events, output: StoreAddBigDecimal) {
 
 #[substreams::handlers::store]
 pub fn store_derived_tvl(
+    params: String,
     clock: Clock,
     events: Events,
     token_total_value_locked: StoreGetBigDecimal, /* store_token_tvl  */
@@ -798,6 +1581,7 @@ pub fn store_derived_tvl(
     eth_prices_store: StoreGetBigDecimal,
     output: StoreSetBigDecimal,
 ) {
+    let config = Config::parse(&params).unwrap();
     let timestamp_seconds = clock.timestamp.unwrap().seconds;
     let day_id: i64 = timestamp_seconds / 86400;
     let hour_id: i64 = timestamp_seconds / 3600;
@@ -856,6 +1640,7 @@ pub fn store_derived_tvl(
             &token0_derive_eth,
             &token1_derive_eth,
             &eth_price_usd,
+            &config,
         );
         // let amounts_for_token = utils::get_adjusted_amounts(
         //     token0_addr,
@@ -1051,6 +1836,42 @@ pub fn store_ticks_liquidities(clock: Clock, events: Events, output: StoreAddBig
     }
 }
 
+/// Count of the initialized ticks in every word of the tick bitmap of a pool,
+/// `word:{pool}:{word}`, moved by the `liquidityGross` of the ticks going from or to zero.
+#[substreams::handlers::store]
+pub fn store_tick_words(
+    ticks_liquidities_deltas: Deltas<DeltaBigInt>, /* store_ticks_liquidities */
+    pools_store: StoreGetProto<Pool>,              /* store_pools_created */
+    output: StoreAddInt64,
+) {
+    for delta in ticks_liquidities_deltas
+        .deltas
+        .iter()
+        .key_first_segment_eq("tick")
+        .operation_not_eq(store_delta::Operation::Delete)
+    {
+        if key::last_segment(&delta.key) != "liquidityGross" {
+            continue;
+        }
+        let pool_address = key::segment_at(&delta.key, 1);
+        let tick_spacing = match pools_store.get_last(format!("pool:{pool_address}")) {
+            Some(pool) if pool.tick_spacing > 0 => pool.tick_spacing,
+            _ => continue,
+        };
+        let initialized = delta.new_value.gt(&BigInt::zero());
+        if initialized == delta.old_value.gt(&BigInt::zero()) {
+            continue;
+        }
+        let tick_idx = key::segment_at(&delta.key, 2).parse::<i32>().unwrap();
+        let word = tick_bitmap::word_position(tick_idx, tick_spacing);
+        output.add(
+            delta.ordinal,
+            format!("word:{pool_address}:{word}"),
+            if initialized { 1 } else { -1 },
+        );
+    }
+}
+
 #[substreams::handlers::store]
 pub fn store_positions(events: Events, output: StoreSetProto<PositionEvent>) {
     let mut positions_events: Vec<PositionEvent> = vec![];
@@ -1135,6 +1956,8 @@ pub fn store_min_windows(
     output.delete_prefix(0, &format!("PoolHourData:{prev_hour_id}:"));
     output.delete_prefix(0, &format!("TokenDayData:{prev_day_id}:"));
     output.delete_prefix(0, &format!("TokenHourData:{prev_hour_id}:"));
+    output.delete_prefix(0, &format!("TokenDayDataETH:{prev_day_id}:"));
+    output.delete_prefix(0, &format!("TokenHourDataETH:{prev_hour_id}:"));
 
     for delta in deltas {
         if delta.operation == store_delta::Operation::Delete {
@@ -1156,6 +1979,8 @@ pub fn store_min_windows(
             }
             "TokenDayData" => "TokenDayData",
             "TokenHourData" => "TokenHourData",
+            "TokenDayDataETH" => "TokenDayDataETH",
+            "TokenHourDataETH" => "TokenHourDataETH",
             _ => continue,
         };
 
@@ -1200,6 +2025,8 @@ pub fn store_max_windows(
     output.delete_prefix(0, &format!("PoolHourData:{prev_hour_id}:"));
     output.delete_prefix(0, &format!("TokenDayData:{prev_day_id}:"));
     output.delete_prefix(0, &format!("TokenHourData:{prev_hour_id}:"));
+    output.delete_prefix(0, &format!("TokenDayDataETH:{prev_day_id}:"));
+    output.delete_prefix(0, &format!("TokenHourDataETH:{prev_hour_id}:"));
 
     for delta in deltas {
         if delta.operation == store_delta::Operation::Delete {
@@ -1221,6 +2048,8 @@ pub fn store_max_windows(
             }
             "TokenDayData" => "TokenDayData",
             "TokenHourData" => "TokenHourData",
+            "TokenDayDataETH" => "TokenDayDataETH",
+            "TokenHourDataETH" => "TokenHourDataETH",
             _ => continue,
         };
 
@@ -1237,10 +2066,12 @@ pub fn store_max_windows(
 
 #[substreams::handlers::map]
 pub fn graph_out(
+    params: String,
     clock: Clock,
     pool_count_deltas: Deltas<DeltaBigInt>,              /* store_pool_count */
     tx_count_deltas: Deltas<DeltaBigInt>,                /* store_total_tx_counts deltas */
     swaps_volume_deltas: Deltas<DeltaBigDecimal>,        /* store_swaps_volume */
+    swaps_volume_store: StoreGetBigDecimal,              /* store_swaps_volume */
     derived_factory_tvl_deltas: Deltas<DeltaBigDecimal>, /* store_derived_factory_tvl */
     derived_eth_prices_deltas: Deltas<DeltaBigDecimal>,  /* store_eth_prices */
     events: Events,                                      /* map_extract_data_types */
@@ -1255,19 +2086,20 @@ pub fn graph_out(
     tokens_whitelist_pools_deltas: Deltas<DeltaArray<String>>, /* store_tokens_whitelist_pools */
     derived_tvl_deltas: Deltas<DeltaBigDecimal>,         /* store_derived_tvl */
     ticks_liquidities_deltas: Deltas<DeltaBigInt>,       /* store_ticks_liquidities */
+    ticks_liquidities_store: StoreGetBigInt,             /* store_ticks_liquidities */
     tx_count_store: StoreGetBigInt,                      /* store_total_tx_counts */
     store_eth_prices: StoreGetBigDecimal,                /* store_eth_prices */
     store_positions: StoreGetProto<PositionEvent>,       /* store_positions */
     min_windows_deltas: Deltas<DeltaBigDecimal>,         /* store_min_windows */
     max_windows_deltas: Deltas<DeltaBigDecimal>,         /* store_max_windows */
 ) -> Result<EntityChanges, Error> {
+    let config = Config::parse(&params)?;
+    let factory_id = config.factory_id();
     let mut tables = Tables::new();
     let timestamp = clock.timestamp.unwrap().seconds;
 
-    if clock.number == 12369621 {
-        // FIXME: Hard-coded start block, how could we pull that from the manifest?
-        // FIXME: ideally taken from the params of the module
-        db::factory_created_factory_entity_change(&mut tables);
+    if clock.number == config.bootstrap_block {
+        db::factory_created_factory_entity_change(&mut tables, &factory_id);
         db::created_bundle_entity_change(&mut tables);
     }
 
@@ -1275,10 +2107,10 @@ pub fn graph_out(
     db::bundle_store_eth_price_usd_bundle_entity_change(&mut tables, &derived_eth_prices_deltas);
 
     // Factory:
-    db::pool_created_factory_entity_change(&mut tables, &pool_count_deltas);
-    db::tx_count_factory_entity_change(&mut tables, &tx_count_deltas);
-    db::swap_volume_factory_entity_change(&mut tables, &swaps_volume_deltas);
-    db::tvl_factory_entity_change(&mut tables, &derived_factory_tvl_deltas);
+    db::pool_created_factory_entity_change(&mut tables, &pool_count_deltas, &factory_id);
+    db::tx_count_factory_entity_change(&mut tables, &tx_count_deltas, &factory_id);
+    db::swap_volume_factory_entity_change(&mut tables, &swaps_volume_deltas, &factory_id);
+    db::tvl_factory_entity_change(&mut tables, &derived_factory_tvl_deltas, &factory_id);
 
     // Pool:
     db::pools_created_pool_entity_changes(&mut tables, &pools_created);
@@ -1306,9 +2138,16 @@ pub fn graph_out(
     db::liquidities_tick_entity_change(&mut tables, &ticks_liquidities_deltas);
 
     // Tick Day/Hour data
-    // db::create_entity_tick_windows(&mut tables, &events.ticks_created);
-    // db::update_tick_windows(&mut tables, &events.ticks_updated);
-    // db::liquidities_tick_windows(&mut tables, &ticks_liquidities_deltas);
+    db::create_entity_tick_windows(
+        &mut tables,
+        &ticks_liquidities_deltas,
+        &swaps_volume_deltas,
+        &ticks_liquidities_store,
+        &swaps_volume_store,
+    );
+    db::update_tick_windows(&mut tables, &events.ticks_updated);
+    db::liquidities_tick_windows(&mut tables, timestamp, &ticks_liquidities_deltas);
+    db::swap_volume_tick_windows(&mut tables, &swaps_volume_deltas);
 
     // Position:
     // TODO: validate all the positions here
@@ -1345,11 +2184,16 @@ pub fn graph_out(
     db::transaction_entity_change(&mut tables, &events.transactions);
 
     // Swap, Mint, Burn:
-    db::swaps_mints_burns_created_entity_change(&mut tables, &events.pool_events, tx_count_store, store_eth_prices);
+    db::swaps_mints_burns_created_entity_change(
+        &mut tables,
+        &events.pool_events,
+        tx_count_store,
+        &store_eth_prices,
+        &config,
+    );
 
     // Flashes:
-    // TODO: should we implement flashes entity change - UNISWAP has not done this part
-    // db::flashes_update_pool_fee_entity_change(&mut tables, events.flashes);
+    db::flashes_created_entity_change(&mut tables, &events.flashes, &store_eth_prices);
 
     // Uniswap day data:
     db::uniswap_day_data_create(&mut tables, &tx_count_deltas);
@@ -0,0 +1,101 @@
+use substreams::scalar::BigInt;
+
+/// Pool ticks kept by `store_pool_ticks`, the median of the last `TICK_HISTORY` ticks of a pool is
+/// its reference price.
+pub const TICK_HISTORY: u64 = 16;
+
+/// Module params of `map_screened_prices` and `store_pool_ticks`, given as `key=value`
+/// pairs separated by `&`, e.g. `max_deviation_bps=2000&min_liquidity=1000000`.
+///
+/// Both checks are disabled with their default of `0`, every price is then accepted.
+#[derive(Debug, Default, PartialEq)]
+pub struct Params {
+    /// Largest distance in basis points of a pool price from its rolling median.
+    pub max_deviation_bps: u64,
+    /// Smallest active liquidity of a pool for its prices to be accepted.
+    pub min_liquidity: u64,
+    /// Rolling medians of fewer ticks than this are not trusted, the price is accepted.
+    pub min_samples: usize,
+}
+
+impl Params {
+    pub fn parse(params: &str) -> Result<Params, substreams::errors::Error> {
+        let mut result = Params {
+            min_samples: 4,
+            ..Default::default()
+        };
+        for pair in params.split('&').map(str::trim).filter(|p| !p.is_empty()) {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("invalid param `{}`, expected key=value", pair))?;
+            let invalid = || anyhow::anyhow!("invalid value `{}` for param `{}`", value, key);
+            match key.trim() {
+                "max_deviation_bps" => result.max_deviation_bps = value.trim().parse().map_err(|_| invalid())?,
+                "min_liquidity" => result.min_liquidity = value.trim().parse().map_err(|_| invalid())?,
+                "min_samples" => result.min_samples = value.trim().parse().map_err(|_| invalid())?,
+                _ => return Err(anyhow::anyhow!("unknown param `{}`", key)),
+            }
+        }
+        Ok(result)
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.max_deviation_bps > 0 || self.min_liquidity > 0
+    }
+
+    /// Active liquidity below the `min_liquidity` floor, always false when the floor is disabled.
+    pub fn below_liquidity_floor(&self, liquidity: &BigInt) -> bool {
+        self.min_liquidity > 0 && liquidity.lt(&BigInt::from(self.min_liquidity))
+    }
+}
+
+/// Median of the ticks, the lower one of the two middle ticks for an even count.
+pub fn median_tick(ticks: &mut [i32]) -> Option<i32> {
+    if ticks.is_empty() {
+        return None;
+    }
+    ticks.sort_unstable();
+    Some(ticks[(ticks.len() - 1) / 2])
+}
+
+/// Distance in basis points between the prices of two ticks, each tick being a 1 bps price move.
+pub fn tick_deviation_bps(tick: i32, median: i32) -> f64 {
+    let ticks = (tick as i64 - median as i64).unsigned_abs() as f64;
+    (1.0001f64.powf(ticks) - 1.0) * 10_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_params() {
+        let params = Params::parse("max_deviation_bps=2000&min_liquidity=1000000").unwrap();
+        assert_eq!(2000, params.max_deviation_bps);
+        assert_eq!(1_000_000, params.min_liquidity);
+        assert_eq!(4, params.min_samples);
+        assert!(params.enabled());
+        assert!(params.below_liquidity_floor(&BigInt::from(999_999)));
+        assert!(!params.below_liquidity_floor(&BigInt::from(1_000_000)));
+
+        assert!(!Params::parse("").unwrap().enabled());
+        assert!(!Params::parse("").unwrap().below_liquidity_floor(&BigInt::zero()));
+        assert!(Params::parse("max_deviation_bps=-1").is_err());
+        assert!(Params::parse("unknown=1").is_err());
+    }
+
+    #[test]
+    fn test_median_tick() {
+        assert_eq!(None, median_tick(&mut []));
+        assert_eq!(Some(-5), median_tick(&mut [10, -5, -200]));
+        assert_eq!(Some(3), median_tick(&mut [7, 1, 3, 5]));
+    }
+
+    #[test]
+    fn test_tick_deviation_bps() {
+        assert_eq!(0.0, tick_deviation_bps(100, 100));
+        let deviation = tick_deviation_bps(-6932, 0);
+        assert!((deviation - 10_000.0).abs() < 1.0, "{}", deviation);
+        assert!((tick_deviation_bps(1, 0) - 1.0).abs() < 1e-9);
+    }
+}
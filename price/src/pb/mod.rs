@@ -4,10 +4,10 @@ use crate::pb::uniswap::events::position_event::Type::{
 };
 use crate::pb::uniswap::events::PoolEvent;
 use crate::pb::uniswap::events::PositionEvent;
-use crate::utils::ERROR_POOL;
-use crate::{Erc20Token, Pool};
+use crate::protocol::Protocol;
+use crate::{Erc20Token, Events, Pool};
+use substreams::log;
 use substreams::scalar::BigDecimal;
-use substreams::{log, Hex};
 
 #[allow(unused_imports)]
 #[allow(dead_code)]
@@ -39,18 +39,26 @@ impl Erc20Token {
 }
 
 impl Pool {
-    pub fn should_handle_swap(&self) -> bool {
-        if self.ignore_pool {
-            return false;
+    /// Pools without a protocol were created before it was recorded, by Uniswap v3.
+    pub fn protocol(&self) -> Protocol {
+        Protocol::from_name(&self.protocol).unwrap_or(Protocol::UniswapV3)
+    }
+
+    /// Name of the protocol of the pool, which `protocol` cannot tell apart for the v4 pools.
+    pub fn protocol_name(&self) -> &str {
+        match self.protocol.as_str() {
+            "" => Protocol::UniswapV3.name(),
+            protocol => protocol,
         }
-        return &self.address != &Hex(ERROR_POOL).to_string();
+    }
+
+    pub fn should_handle_swap(&self) -> bool {
+        // The error pool of the chain config is flagged when created
+        !self.ignore_pool
     }
 
     pub fn should_handle_mint_and_burn(&self) -> bool {
-        if self.ignore_pool {
-            return false;
-        }
-        return &self.address != &Hex(ERROR_POOL).to_string();
+        !self.ignore_pool
     }
 
     pub fn token0_ref(&self) -> &Erc20Token {
@@ -119,3 +127,21 @@ impl PoolEvent {
         };
     }
 }
+
+impl Events {
+    /// Adds the events of the Uniswap v4 pools, which only fill the pool prices, liquidities,
+    /// fee growths, events and transactions, keeping every list in ordinal order.
+    pub fn merge_v4(&mut self, v4_events: Events) {
+        self.pool_sqrt_prices.extend(v4_events.pool_sqrt_prices);
+        self.pool_sqrt_prices.sort_by_key(|sqrt_price| sqrt_price.ordinal);
+        self.pool_liquidities.extend(v4_events.pool_liquidities);
+        self.pool_liquidities.sort_by_key(|liquidity| liquidity.log_ordinal);
+        self.fee_growth_global_updates
+            .extend(v4_events.fee_growth_global_updates);
+        self.fee_growth_global_updates.sort_by_key(|update| update.ordinal);
+        self.pool_events.extend(v4_events.pool_events);
+        self.pool_events.sort_by_key(|event| event.log_ordinal);
+        self.transactions.extend(v4_events.transactions);
+        self.transactions.sort_by_key(|transaction| transaction.log_ordinal);
+    }
+}
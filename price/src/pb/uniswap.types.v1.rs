@@ -38,6 +38,36 @@ pub struct Pools {
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RejectedPrices {
+    #[prost(message, repeated, tag="1")]
+    pub rejected_prices: ::prost::alloc::vec::Vec<RejectedPrice>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RejectedPrice {
+    #[prost(string, tag="1")]
+    pub pool_address: ::prost::alloc::string::String,
+    #[prost(uint64, tag="2")]
+    pub ordinal: u64,
+    /// Integer
+    #[prost(string, tag="3")]
+    pub sqrt_price: ::prost::alloc::string::String,
+    /// Integer
+    #[prost(string, tag="4")]
+    pub tick: ::prost::alloc::string::String,
+    /// Integer
+    #[prost(string, tag="5")]
+    pub median_tick: ::prost::alloc::string::String,
+    #[prost(double, tag="6")]
+    pub deviation_bps: f64,
+    /// Integer
+    #[prost(string, tag="7")]
+    pub liquidity: ::prost::alloc::string::String,
+    #[prost(string, tag="8")]
+    pub reason: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Pool {
     #[prost(string, tag="1")]
     pub address: ::prost::alloc::string::String,
@@ -52,6 +82,15 @@ pub struct Pool {
     /// Integer
     #[prost(string, tag="7")]
     pub fee_tier: ::prost::alloc::string::String,
+    /// uniswap_v3, sushiswap_v3, pancakeswap_v3, uniswap_v4 or uniswap_v2 for the v2 pairs
+    #[prost(string, tag="8")]
+    pub protocol: ::prost::alloc::string::String,
+    /// uniswap_v4 hooks contract, empty without hooks
+    #[prost(string, tag="9")]
+    pub hooks: ::prost::alloc::string::String,
+    /// uniswap_v4 pool whose hooks set the fee of every swap, its fee_tier is 0
+    #[prost(bool, tag="10")]
+    pub dynamic_fee: bool,
     /// internals
     #[prost(int32, tag="30")]
     pub tick_spacing: i32,
@@ -219,6 +258,8 @@ pub mod events {
         pub timestamp: u64,
         #[prost(uint64, tag="108")]
         pub created_at_block_number: u64,
+        #[prost(string, tag="109")]
+        pub protocol: ::prost::alloc::string::String,
         #[prost(oneof="pool_event::Type", tags="1, 2, 3")]
         pub r#type: ::core::option::Option<pool_event::Type>,
     }
@@ -337,6 +378,32 @@ pub mod events {
         pub fee_growth_global_1x_128: ::prost::alloc::string::String,
         #[prost(uint64, tag="4")]
         pub log_ordinal: u64,
+        #[prost(string, tag="5")]
+        pub sender: ::prost::alloc::string::String,
+        #[prost(string, tag="6")]
+        pub recipient: ::prost::alloc::string::String,
+        #[prost(string, tag="7")]
+        pub token0: ::prost::alloc::string::String,
+        #[prost(string, tag="8")]
+        pub token1: ::prost::alloc::string::String,
+        /// Decimal
+        #[prost(string, tag="9")]
+        pub amount_0: ::prost::alloc::string::String,
+        /// Decimal
+        #[prost(string, tag="10")]
+        pub amount_1: ::prost::alloc::string::String,
+        /// Decimal
+        #[prost(string, tag="11")]
+        pub paid_0: ::prost::alloc::string::String,
+        /// Decimal
+        #[prost(string, tag="12")]
+        pub paid_1: ::prost::alloc::string::String,
+        #[prost(string, tag="13")]
+        pub transaction_id: ::prost::alloc::string::String,
+        #[prost(uint64, tag="14")]
+        pub timestamp: u64,
+        #[prost(uint64, tag="15")]
+        pub log_index: u64,
     }
     #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
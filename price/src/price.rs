@@ -1,4 +1,5 @@
-use crate::{math, Erc20Token, Pool};
+use crate::config::Config;
+use crate::{math, stablecoins, Erc20Token, Pool};
 use std::ops::{Div, Mul};
 use std::str;
 use std::str::FromStr;
@@ -6,43 +7,6 @@ use substreams::log;
 use substreams::scalar::{BigDecimal, BigInt};
 use substreams::store::{StoreGet, StoreGetBigDecimal, StoreGetBigInt, StoreGetProto, StoreGetRaw};
 
-const USDC_WETH_03_POOL: &str = "8ad599c3a0ff1de082011efddc58f1908eb6e6d8";
-const USDC_ADDRESS: &str = "a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
-const WETH_ADDRESS: &str = "c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2";
-
-pub const STABLE_COINS: [&str; 6] = [
-    "6b175474e89094c44da98b954eedeac495271d0f", // DAI
-    "a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48", // USDC
-    "dac17f958d2ee523a2206206994597c13d831ec7", // USDT
-    "0000000000085d4780b73119b644ae5ecd22b376", // TUSD
-    "956f47f50a910163d8bf957cf5846d573e7f87ca", // FEI
-    "4dd28568d05f09b02220b09c2cb307bfd837cb95", // PRINTS
-];
-
-pub const WHITELIST_TOKENS: [&str; 21] = [
-    "c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2", // WETH
-    "6b175474e89094c44da98b954eedeac495271d0f", // DAI
-    "a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48", // USDC
-    "dac17f958d2ee523a2206206994597c13d831ec7", // USDT
-    "0000000000085d4780b73119b644ae5ecd22b376", // TUSD
-    "2260fac5e5542a773aa44fbcfedf7c193bc2c599", // WBTC
-    "5d3a536e4d6dbd6114cc1ead35777bab948e3643", // cDAI
-    "39aa39c021dfbae8fac545936693ac917d5e7563", // cUSDC
-    "86fadb80d8d2cff3c3680819e4da99c10232ba0f", // EBASE
-    "57ab1ec28d129707052df4df418d58a2d46d5f51", // sUSD
-    "9f8f72aa9304c8b593d555f12ef6589cc3a579a2", // MKR
-    "c00e94cb662c3520282e6f5717214004a7f26888", // COMP
-    "514910771af9ca656af840dff83e8264ecf986ca", // LINK
-    "c011a73ee8576fb46f5e1c5751ca3b9fe0af2a6f", // SNX
-    "0bc529c00c6401aef6d220be8c6ea1667f6ad93e", // YFI
-    "111111111117dc0aa78b770fa6a738034120c302", // 1INCH
-    "df5e0e81dff6faf3a7e52ba697820c5e32d806a8", // yCurv
-    "956f47f50a910163d8bf957cf5846d573e7f87ca", // FEI
-    "7d1afa7b718fb893db30a3abc0cfc608aacfebb0", // MATIC
-    "7fc66500c84a76ad7e9c93437bfc5ac33e2ddae9", // AAVE
-    "fe2e637202056d30016725477c5da089ab0a043a", // sETH2
-];
-
 pub fn sqrt_price_x96_to_token_prices(
     sqrt_price: BigDecimal,
     token_0: &Erc20Token,
@@ -70,7 +34,12 @@ pub fn sqrt_price_x96_to_token_prices(
     return (price0, price1);
 }
 
-pub fn find_eth_per_token(
+/// Price of the token in ETH, along with the pool the price was read from. WETH is priced from
+/// the `usd_pool` of the config, the source is empty for stablecoins and tokens without a price.
+///
+/// Stablecoins are worth one dollar, unless `store_stablecoin_pegs` reports them depegged. They
+/// are then priced from their pools like any other token.
+pub fn find_eth_per_token_and_source(
     ord: u64,
     pool_address: &String,
     token_address: &String,
@@ -79,18 +48,22 @@ pub fn find_eth_per_token(
     tokens_whitelist_pools_store: &StoreGetRaw,
     total_native_amounts_store: &StoreGetBigDecimal,
     prices_store: &StoreGetBigDecimal,
-) -> BigDecimal {
+    stablecoin_pegs_store: &StoreGetBigDecimal,
+    config: &Config,
+) -> (BigDecimal, String) {
     log::debug!("finding ETH per token for {} in pool {}", token_address, pool_address);
-    if token_address.eq(WETH_ADDRESS) {
+    let weth_address = &config.weth;
+    if token_address.eq(weth_address) {
         log::debug!("is ETH return 1");
-        return BigDecimal::one();
+        return (BigDecimal::one(), config.usd_pool.clone());
     }
 
     let mut price_so_far = BigDecimal::zero();
+    let mut source_pool = String::new();
 
-    if STABLE_COINS.contains(&token_address.as_str()) {
+    if config.is_stable_coin(token_address) && !is_depegged(stablecoin_pegs_store, ord, token_address) {
         log::debug!("token addr: {} is a stable coin", token_address);
-        let eth_price_usd = get_eth_price_in_usd(prices_store, ord);
+        let eth_price_usd = get_pegged_eth_price_in_usd(prices_store, stablecoin_pegs_store, ord, config);
         log::info!("eth_price_usd {}", eth_price_usd);
         price_so_far = math::safe_div(&BigDecimal::one(), &eth_price_usd);
     } else {
@@ -98,7 +71,7 @@ pub fn find_eth_per_token(
         let wl = match tokens_whitelist_pools_store.get_last(&format!("token:{token_address}")) {
             None => {
                 log::debug!("failed to get whitelisted pools for token {}", token_address);
-                return BigDecimal::zero();
+                return (BigDecimal::zero(), source_pool);
             }
             Some(bytes) => String::from_utf8(bytes.to_vec()).unwrap(),
         };
@@ -112,7 +85,7 @@ pub fn find_eth_per_token(
         log::debug!("found whitelisted pools {}", whitelisted_pools.len());
 
         let mut largest_eth_locked = BigDecimal::zero();
-        let minimum_eth_locked = BigDecimal::from_str("52").unwrap();
+        let minimum_eth_locked = BigDecimal::from(config.minimum_eth_locked);
         let mut eth_locked: BigDecimal;
 
         for pool_address in whitelisted_pools.iter() {
@@ -153,14 +126,14 @@ pub fn find_eth_per_token(
 
                     let token1_eth_price;
                     // If the counter token is WETH we know the derived price is 1
-                    if token1.address.eq(WETH_ADDRESS) {
+                    if token1.address.eq(weth_address) {
                         log::debug!("token 1 is WETH");
                         eth_locked = native_amount;
                         token1_eth_price = BigDecimal::one();
                     } else {
                         log::debug!("token 1 is NOT WETH");
 
-                        match pool_liquidities_store.get_at(ord, format!("pair:{WETH_ADDRESS}:{token1_addr}")) {
+                        match pool_liquidities_store.get_at(ord, format!("pair:{weth_address}:{token1_addr}")) {
                             None => {
                                 log::debug!("unable to find liquidity for {:?}", token1_addr);
                                 continue;
@@ -176,7 +149,7 @@ pub fn find_eth_per_token(
                             }
                         }
 
-                        token1_eth_price = match prices_store.get_at(ord, format!("pair:{WETH_ADDRESS}:{token1_addr}"))
+                        token1_eth_price = match prices_store.get_at(ord, format!("pair:{weth_address}:{token1_addr}"))
                         {
                             None => {
                                 log::debug!("unable to find token 1 price in eth {token1_addr}");
@@ -195,7 +168,7 @@ pub fn find_eth_per_token(
                     );
                     // should the check below make more sens if we EITHER have eth.gt > largest && (eth_locked > min BUT !Whitelist || whitelist)???
                     if eth_locked.gt(&largest_eth_locked)
-                        && (eth_locked.gt(&minimum_eth_locked) || WHITELIST_TOKENS.contains(&token0_addr.as_str()))
+                        && (eth_locked.gt(&minimum_eth_locked) || config.is_whitelisted(token0_addr))
                     {
                         log::debug!("eth locked passed test");
                         let token1_price =
@@ -209,6 +182,7 @@ pub fn find_eth_per_token(
                         log::debug!("found token 1 price {}", token1_price);
                         largest_eth_locked = eth_locked.clone();
                         price_so_far = token1_price.mul(token1_eth_price.clone());
+                        source_pool = pool_address.to_string();
                         log::debug!("price_so_far {}", price_so_far);
                     }
                 }
@@ -229,14 +203,14 @@ pub fn find_eth_per_token(
                     let token0_eth_price ;
 
                     // If the counter token is WETH we know the derived price is 1
-                    if token0.address.eq(WETH_ADDRESS) {
+                    if token0.address.eq(weth_address) {
                         log::debug!("token 0 is WETH");
                         eth_locked = native_amount;
                         token0_eth_price = BigDecimal::one();
                     } else {
                         log::debug!("token 0 is NOT WETH");
 
-                        match pool_liquidities_store.get_at(ord, format!("pair:{WETH_ADDRESS}:{token0_addr}")) {
+                        match pool_liquidities_store.get_at(ord, format!("pair:{weth_address}:{token0_addr}")) {
                             None => {
                                 log::debug!("unable to find liquidity for {:?}", token0_addr);
                                 continue;
@@ -252,7 +226,7 @@ pub fn find_eth_per_token(
                             }
                         }
 
-                        token0_eth_price = match prices_store.get_at(ord, format!("pair:{WETH_ADDRESS}:{token0_addr}"))
+                        token0_eth_price = match prices_store.get_at(ord, format!("pair:{weth_address}:{token0_addr}"))
                         {
                             None => {
                                 log::debug!("unable to find token 0 price in eth {:?}", token0.address);
@@ -266,7 +240,7 @@ pub fn find_eth_per_token(
                     }
                     log::debug!("eth locked in pool {pool_address} {eth_locked} (largest {largest_eth_locked})",);
                     if eth_locked.gt(&largest_eth_locked)
-                        && (eth_locked.gt(&minimum_eth_locked) || WHITELIST_TOKENS.contains(&token1_addr.as_str()))
+                        && (eth_locked.gt(&minimum_eth_locked) || config.is_whitelisted(token1_addr))
                     {
                         log::debug!("eth locked passed test");
                         let token0_price =
@@ -280,17 +254,19 @@ pub fn find_eth_per_token(
                         log::debug!("found token 0 price {}", token0_price);
                         largest_eth_locked = eth_locked.clone();
                         price_so_far = token0_price.mul(token0_eth_price.clone());
+                        source_pool = pool_address.to_string();
                         log::debug!("price_so_far {}", price_so_far);
                     }
                 }
             }
         }
     }
-    return price_so_far;
+    return (price_so_far, source_pool);
 }
 
-pub fn get_eth_price_in_usd(prices_store: &StoreGetBigDecimal, ordinal: u64) -> BigDecimal {
-    let key = format!("pool:{}:{}:{}", USDC_WETH_03_POOL, USDC_ADDRESS, "token0");
+pub fn get_eth_price_in_usd(prices_store: &StoreGetBigDecimal, ordinal: u64, config: &Config) -> BigDecimal {
+    let side = config.usd_token_side();
+    let key = format!("pool:{}:{}:{side}", config.usd_pool, config.usd_token);
     return match prices_store.get_at(ordinal, &key) {
         None => {
             log::debug!("price not found");
@@ -299,3 +275,32 @@ pub fn get_eth_price_in_usd(prices_store: &StoreGetBigDecimal, ordinal: u64) ->
         Some(price) => price,
     };
 }
+
+/// USD price of ETH of `get_eth_price_in_usd`, taken from the WETH pairs of the other stablecoins
+/// while `store_stablecoin_pegs` reports `usd_token` depegged.
+pub fn get_pegged_eth_price_in_usd(
+    prices_store: &StoreGetBigDecimal,
+    stablecoin_pegs_store: &StoreGetBigDecimal,
+    ordinal: u64,
+    config: &Config,
+) -> BigDecimal {
+    let usd_pool_price = get_eth_price_in_usd(prices_store, ordinal, config);
+    if !is_depegged(stablecoin_pegs_store, ordinal, &config.usd_token) {
+        return usd_pool_price;
+    }
+    log::info!("usd token {} is depegged", config.usd_token);
+    let pegged_prices = config
+        .stable_coins
+        .iter()
+        .filter(|stablecoin| **stablecoin != config.usd_token)
+        .filter(|stablecoin| !is_depegged(stablecoin_pegs_store, ordinal, stablecoin))
+        .filter_map(|stablecoin| prices_store.get_at(ordinal, format!("pair:{stablecoin}:{}", config.weth)))
+        .collect();
+    stablecoins::eth_price_in_usd(usd_pool_price, true, pegged_prices)
+}
+
+fn is_depegged(stablecoin_pegs_store: &StoreGetBigDecimal, ordinal: u64, token_address: &str) -> bool {
+    stablecoin_pegs_store
+        .get_at(ordinal, format!("depegged:{token_address}"))
+        .map_or(false, |depegged| depegged.eq(&BigDecimal::one()))
+}
@@ -0,0 +1,127 @@
+use crate::abi::pool::events::Swap;
+use crate::storage::uniswap_v3_pool::PoolLayout;
+use crate::EventTrait;
+use substreams::hex;
+use substreams::scalar::BigInt;
+use substreams_ethereum::pb::eth::v2::Log;
+
+/// `Swap(address,address,int256,int256,uint160,uint128,int24,uint128,uint128)`, the Uniswap v3
+/// swap followed by the protocol fees of both tokens.
+const PANCAKE_V3_SWAP_TOPIC: [u8; 32] = hex!("19b47279256b2a23a1665c810c8d55a1758940ee09377d4f8d26497a3577dc83");
+
+/// v3-style DEX sharing the Uniswap v3 pool ABI, the `protocol` of its pools and events.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Protocol {
+    UniswapV3,
+    SushiswapV3,
+    PancakeswapV3,
+}
+
+impl Protocol {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Protocol::UniswapV3 => "uniswap_v3",
+            Protocol::SushiswapV3 => "sushiswap_v3",
+            Protocol::PancakeswapV3 => "pancakeswap_v3",
+        }
+    }
+
+    /// Pools stored before they had a protocol are Uniswap v3 pools.
+    pub fn from_name(name: &str) -> Option<Protocol> {
+        match name {
+            "" | "uniswap_v3" => Some(Protocol::UniswapV3),
+            "sushiswap_v3" => Some(Protocol::SushiswapV3),
+            "pancakeswap_v3" => Some(Protocol::PancakeswapV3),
+            _ => None,
+        }
+    }
+
+    pub fn pool_layout(&self) -> PoolLayout {
+        match self {
+            Protocol::UniswapV3 | Protocol::SushiswapV3 => PoolLayout::UniswapV3,
+            Protocol::PancakeswapV3 => PoolLayout::PancakeV3,
+        }
+    }
+
+    pub fn match_swap(&self, log: &Log) -> bool {
+        match self {
+            Protocol::PancakeswapV3 => log
+                .topics
+                .first()
+                .map_or(false, |topic| topic == &PANCAKE_V3_SWAP_TOPIC),
+            _ => Swap::match_log(log),
+        }
+    }
+
+    /// Swap of a pool of the protocol, the PancakeSwap v3 protocol fees are left out.
+    pub fn decode_swap(&self, log: &Log) -> Option<Swap> {
+        match self {
+            Protocol::PancakeswapV3 => decode_pancake_v3_swap(&log.topics, &log.data),
+            _ => Swap::match_and_decode(log),
+        }
+    }
+}
+
+/// Factory and position manager of a protocol on the indexed chain.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Profile {
+    pub protocol: Protocol,
+    pub factory: Vec<u8>,
+    pub position_manager: Vec<u8>,
+}
+
+fn decode_pancake_v3_swap(topics: &[Vec<u8>], data: &[u8]) -> Option<Swap> {
+    if topics.len() != 3 || topics[0] != PANCAKE_V3_SWAP_TOPIC || data.len() < 7 * 32 {
+        return None;
+    }
+    Some(Swap {
+        sender: topics[1][12..32].to_vec(),
+        recipient: topics[2][12..32].to_vec(),
+        amount0: BigInt::from_signed_bytes_be(&data[0..32]),
+        amount1: BigInt::from_signed_bytes_be(&data[32..64]),
+        sqrt_price_x96: BigInt::from_unsigned_bytes_be(&data[64..96]),
+        liquidity: BigInt::from_unsigned_bytes_be(&data[96..128]),
+        tick: BigInt::from_signed_bytes_be(&data[128..160]),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(value: i64) -> Vec<u8> {
+        let fill = if value < 0 { 0xff } else { 0 };
+        let mut word = vec![fill; 24];
+        word.extend_from_slice(&value.to_be_bytes());
+        word
+    }
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(Some(Protocol::UniswapV3), Protocol::from_name(""));
+        assert_eq!(Some(Protocol::PancakeswapV3), Protocol::from_name("pancakeswap_v3"));
+        assert_eq!(None, Protocol::from_name("uniswap_v4"));
+        assert_eq!(PoolLayout::UniswapV3, Protocol::SushiswapV3.pool_layout());
+    }
+
+    #[test]
+    fn test_decode_pancake_v3_swap() {
+        let mut sender = vec![0u8; 12];
+        sender.extend_from_slice(&hex!("13f4ea83d0bd40e75c8222255bc855a974568dd4"));
+        let mut recipient = vec![0u8; 12];
+        recipient.extend_from_slice(&hex!("1b81d678ffb9c0263b24a97847620c99d213eb14"));
+        let topics = vec![PANCAKE_V3_SWAP_TOPIC.to_vec(), sender, recipient];
+        let data: Vec<u8> = [-5000, 2_000_000, 79_228_162_514, 10_000, -276_324, 1, 0]
+            .iter()
+            .flat_map(|value| word(*value))
+            .collect();
+
+        let swap = decode_pancake_v3_swap(&topics, &data).unwrap();
+        assert_eq!(hex!("13f4ea83d0bd40e75c8222255bc855a974568dd4").to_vec(), swap.sender);
+        assert_eq!(BigInt::from(-5000), swap.amount0);
+        assert_eq!(BigInt::from(2_000_000), swap.amount1);
+        assert_eq!(BigInt::from(79_228_162_514u64), swap.sqrt_price_x96);
+        assert_eq!(BigInt::from(-276_324), swap.tick);
+        assert!(decode_pancake_v3_swap(&topics, &data[..160]).is_none());
+    }
+}
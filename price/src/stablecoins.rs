@@ -0,0 +1,177 @@
+use crate::config;
+use std::cmp::Ordering;
+use substreams::scalar::BigDecimal;
+
+/// Params read by `Params::parse`, the chain params of `store_stablecoin_pegs` go to `config::Config`.
+pub const KEYS: [&str; 1] = ["band_bps"];
+
+/// Module params of `store_stablecoin_pegs`, e.g. `band_bps=200`.
+#[derive(Debug, PartialEq)]
+pub struct Params {
+    /// A stablecoin further than this from the median price of the other ones is depegged.
+    pub band_bps: u64,
+}
+
+impl Params {
+    pub fn parse(params: &str) -> Result<Params, substreams::errors::Error> {
+        let mut result = Params { band_bps: 200 };
+        for pair in params.split('&').map(str::trim).filter(|p| !p.is_empty()) {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("invalid param `{}`, expected key=value", pair))?;
+            match key.trim() {
+                "band_bps" => {
+                    result.band_bps = value
+                        .trim()
+                        .parse()
+                        .map_err(|_| anyhow::anyhow!("invalid band_bps `{}`", value))?
+                }
+                key if config::KEYS.contains(&key) => {}
+                _ => return Err(anyhow::anyhow!("unknown param `{}`", key)),
+            }
+        }
+        Ok(result)
+    }
+}
+
+/// Price of every stablecoin divided by the median price of the other ones. Stablecoins are left
+/// out while fewer than two other ones have a price.
+pub fn peg_ratios(prices: &[(String, BigDecimal)]) -> Vec<(String, BigDecimal)> {
+    prices
+        .iter()
+        .filter_map(|(address, price)| {
+            let mut others: Vec<&BigDecimal> = prices
+                .iter()
+                .filter(|(other, _)| other != address)
+                .map(|(_, price)| price)
+                .collect();
+            if others.len() < 2 {
+                return None;
+            }
+            others.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+            let median = others[(others.len() - 1) / 2];
+            if median.eq(&BigDecimal::zero()) {
+                return None;
+            }
+            Some((address.clone(), price.clone() / median.clone()))
+        })
+        .collect()
+}
+
+pub fn is_depegged(peg_ratio: &BigDecimal, band_bps: u64) -> bool {
+    let deviation_bps = (peg_ratio.clone() - BigDecimal::one()).absolute() * BigDecimal::from(10000);
+    deviation_bps.gt(&BigDecimal::from(band_bps))
+}
+
+/// Price in `usd_token` of one dollar: the median price of the stablecoins still pegged while
+/// `usd_token` is depegged, one otherwise. The prices in `usd_token` divided by it are USD prices.
+pub fn usd_token_scale(
+    prices: &[(String, BigDecimal)],
+    peg_ratios: &[(String, BigDecimal)],
+    usd_token: &str,
+    band_bps: u64,
+) -> BigDecimal {
+    let depegged = |address: &str| {
+        peg_ratios
+            .iter()
+            .find(|(stablecoin, _)| stablecoin == address)
+            .map(|(_, peg_ratio)| is_depegged(peg_ratio, band_bps))
+    };
+    if depegged(usd_token) != Some(true) {
+        return BigDecimal::one();
+    }
+    let mut pegged_prices: Vec<&BigDecimal> = prices
+        .iter()
+        .filter(|(address, _)| depegged(address) == Some(false))
+        .map(|(_, price)| price)
+        .collect();
+    if pegged_prices.is_empty() {
+        return BigDecimal::one();
+    }
+    pegged_prices.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    pegged_prices[(pegged_prices.len() - 1) / 2].clone()
+}
+
+/// USD price of ETH, the one read from `usd_pool` unless its `usd_token` is depegged. The median
+/// of the ETH prices in the stablecoins still pegged is then used, `usd_pool` being kept when none
+/// of them has a price.
+pub fn eth_price_in_usd(
+    usd_pool_price: BigDecimal,
+    usd_token_depegged: bool,
+    mut pegged_prices: Vec<BigDecimal>,
+) -> BigDecimal {
+    if !usd_token_depegged || pegged_prices.is_empty() {
+        return usd_pool_price;
+    }
+    pegged_prices.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    pegged_prices.swap_remove((pegged_prices.len() - 1) / 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn price(address: &str, price: &str) -> (String, BigDecimal) {
+        (address.to_string(), BigDecimal::from_str(price).unwrap())
+    }
+
+    #[test]
+    fn test_peg_ratios() {
+        let prices = vec![
+            price("usdc", "0.88"),
+            price("dai", "1.001"),
+            price("usdt", "0.999"),
+            price("tusd", "1.0"),
+        ];
+        let ratios = peg_ratios(&prices);
+        assert_eq!(4, ratios.len());
+        assert_eq!(BigDecimal::from_str("0.88").unwrap(), ratios[0].1);
+        assert!(is_depegged(&ratios[0].1, 200));
+        assert!(!is_depegged(&ratios[1].1, 200));
+        assert!(!is_depegged(&ratios[2].1, 200));
+
+        assert!(peg_ratios(&prices[..2]).is_empty());
+    }
+
+    #[test]
+    fn test_usd_token_scale() {
+        let prices = vec![
+            price("usdc", "1"),
+            price("dai", "1.136"),
+            price("usdt", "1.13"),
+            price("tusd", "1.14"),
+        ];
+        let ratios = peg_ratios(&prices);
+        let scale = usd_token_scale(&prices, &ratios, "usdc", 200);
+        assert_eq!(BigDecimal::from_str("1.136").unwrap(), scale);
+        let usdc_usd = prices[0].1.clone() / scale;
+        assert!(usdc_usd.lt(&BigDecimal::from_str("0.89").unwrap()));
+
+        assert_eq!(BigDecimal::one(), usd_token_scale(&prices, &ratios, "dai", 200));
+        assert_eq!(BigDecimal::one(), usd_token_scale(&prices[..2], &[], "usdc", 200));
+    }
+
+    #[test]
+    fn test_eth_price_in_depegged_usd_token() {
+        let usd_pool_price = BigDecimal::from(3400);
+        let pegged_prices = vec![BigDecimal::from(3010), BigDecimal::from(2990), BigDecimal::from(3000)];
+        assert_eq!(
+            BigDecimal::from(3000),
+            eth_price_in_usd(usd_pool_price.clone(), true, pegged_prices.clone())
+        );
+        assert_eq!(
+            usd_pool_price,
+            eth_price_in_usd(usd_pool_price.clone(), false, pegged_prices)
+        );
+        assert_eq!(usd_pool_price, eth_price_in_usd(usd_pool_price.clone(), true, vec![]));
+    }
+
+    #[test]
+    fn test_parse_params() {
+        assert_eq!(200, Params::parse("").unwrap().band_bps);
+        assert_eq!(50, Params::parse("band_bps=50").unwrap().band_bps);
+        assert!(Params::parse("band_bps=wide").is_err());
+        assert_eq!(50, Params::parse("chain=arbitrum-one&band_bps=50").unwrap().band_bps);
+    }
+}
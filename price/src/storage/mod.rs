@@ -6,4 +6,8 @@ pub mod uniswap_v3_pool;
 #[allow(dead_code)]
 pub mod position_manager;
 
+#[allow(unused_imports)]
+#[allow(dead_code)]
+pub mod pool_manager;
+
 mod utils;
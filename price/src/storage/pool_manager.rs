@@ -0,0 +1,89 @@
+use crate::storage::utils;
+use substreams::scalar::BigInt;
+use substreams_ethereum::pb::eth::v2::StorageChange;
+
+/// Slot of the `_pools` mapping of the Uniswap v4 `PoolManager`, from pool id to its `Pool.State`.
+const POOLS_SLOT: i32 = 6;
+
+/// State of a pool of the Uniswap v4 singleton, stored in the `PoolManager` under its pool id.
+pub struct PoolManagerStorage<'a> {
+    pub storage_changes: &'a Vec<StorageChange>,
+    pub contract_addr: [u8; 20],
+    pub state_slot: [u8; 32],
+}
+
+impl<'a> PoolManagerStorage<'a> {
+    pub fn new(
+        storage_changes: &'a Vec<StorageChange>,
+        contract_addr: &Vec<u8>,
+        pool_id: &Vec<u8>,
+    ) -> PoolManagerStorage<'a> {
+        let pools_slot = utils::left_pad_from_bigint(&BigInt::from(POOLS_SLOT));
+        return Self {
+            storage_changes,
+            contract_addr: utils::contract_pad(contract_addr),
+            state_slot: utils::calc_map_slot(&utils::left_pad(pool_id, 0), &pools_slot),
+        };
+    }
+
+    pub fn fee_growth_global0x128(&self) -> Option<(BigInt, BigInt)> {
+        self.read_unsigned(1, 32)
+    }
+
+    pub fn fee_growth_global1x128(&self) -> Option<(BigInt, BigInt)> {
+        self.read_unsigned(2, 32)
+    }
+
+    pub fn liquidity(&self) -> Option<(BigInt, BigInt)> {
+        self.read_unsigned(3, 16)
+    }
+
+    fn read_unsigned(&self, member_slot: i32, number_of_bytes: usize) -> Option<(BigInt, BigInt)> {
+        let slot_key = utils::calc_struct_slot(&self.state_slot, BigInt::from(member_slot));
+        let offset = 0;
+
+        if let Some((old_data, new_data)) =
+            utils::get_storage_change(&self.filtered_changes(), slot_key, offset, number_of_bytes)
+        {
+            Some((
+                BigInt::from_unsigned_bytes_be(old_data),
+                BigInt::from_unsigned_bytes_be(new_data),
+            ))
+        } else {
+            None
+        }
+    }
+
+    fn filtered_changes(&self) -> Vec<&StorageChange> {
+        return self
+            .storage_changes
+            .iter()
+            .filter(|change| change.address == self.contract_addr)
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::storage::pool_manager::PoolManagerStorage;
+    use substreams::hex;
+    use substreams::scalar::BigInt;
+    use substreams_ethereum::pb::eth::v2::StorageChange;
+
+    #[test]
+    fn pool_liquidity() {
+        let pool_manager = hex!("000000000004444c5dc75cb358380d2e3de08a90").to_vec();
+        let pool_id = hex!("21c67e77068de97969ba93d4aab21826d33ca12bb9f565d8496e8fda8a82ca27").to_vec();
+        let storage_changes = vec![StorageChange {
+            address: pool_manager.clone(),
+            key: hex!("da8cac368d67cd2f2d8aaa5cc531768e0fa3b1d205c5c5de60da078e1f59bdff").to_vec(),
+            old_value: hex!("0000000000000000000000000000000000000000000000000000000000000000").to_vec(),
+            new_value: hex!("0000000000000000000000000000000000000000000000000000000000002710").to_vec(),
+            ordinal: 0,
+        }];
+
+        let storage = PoolManagerStorage::new(&storage_changes, &pool_manager, &pool_id);
+        assert_eq!(Some((BigInt::zero(), BigInt::from(10000))), storage.liquidity());
+        assert_eq!(None, storage.fee_growth_global0x128());
+    }
+}
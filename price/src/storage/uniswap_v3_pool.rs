@@ -3,21 +3,49 @@ use hex::encode;
 use substreams::scalar::BigInt;
 use substreams_ethereum::pb::eth::v2::StorageChange;
 
+/// Storage layout of a v3 pool. PancakeSwap v3 widens `slot0.feeProtocol` to 32 bits, which
+/// moves it and `unlocked` to a second slot and every state variable after `slot0` one slot down.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PoolLayout {
+    UniswapV3,
+    PancakeV3,
+}
+
+impl PoolLayout {
+    /// Slot of a state variable declared after `slot0`, given its Uniswap v3 slot.
+    fn slot(&self, uniswap_slot: i32) -> BigInt {
+        match self {
+            PoolLayout::UniswapV3 => BigInt::from(uniswap_slot),
+            PoolLayout::PancakeV3 => BigInt::from(uniswap_slot + 1),
+        }
+    }
+}
+
 pub struct UniswapPoolStorage<'a> {
     pub storage_changes: &'a Vec<StorageChange>,
     pub contract_addr: [u8; 20],
+    pub layout: PoolLayout,
 }
 
 impl<'a> UniswapPoolStorage<'a> {
     pub fn new(storage_changes: &'a Vec<StorageChange>, contract_addr: &Vec<u8>) -> UniswapPoolStorage<'a> {
+        Self::with_layout(storage_changes, contract_addr, PoolLayout::UniswapV3)
+    }
+
+    pub fn with_layout(
+        storage_changes: &'a Vec<StorageChange>,
+        contract_addr: &Vec<u8>,
+        layout: PoolLayout,
+    ) -> UniswapPoolStorage<'a> {
         return Self {
             storage_changes,
             contract_addr: utils::contract_pad(contract_addr),
+            layout,
         };
     }
 
     pub fn fee_growth_global0x128(&self) -> Option<(BigInt, BigInt)> {
-        let fee_growth_global0x128_slot = BigInt::from(1);
+        let fee_growth_global0x128_slot = self.layout.slot(1);
         let offset = 0;
         let number_of_bytes = 32;
 
@@ -38,7 +66,7 @@ impl<'a> UniswapPoolStorage<'a> {
     }
 
     pub fn fee_growth_global1x128(&self) -> Option<(BigInt, BigInt)> {
-        let fee_growth_global1x128_slot = BigInt::from(2);
+        let fee_growth_global1x128_slot = self.layout.slot(2);
         let offset = 0;
         let number_of_bytes = 32;
 
@@ -59,7 +87,7 @@ impl<'a> UniswapPoolStorage<'a> {
     }
 
     pub fn liquidity(&self) -> Option<(BigInt, BigInt)> {
-        let liquidity_slot = BigInt::from(4);
+        let liquidity_slot = self.layout.slot(4);
         let offset = 0;
         let number_of_bytes = 16;
 
@@ -81,11 +109,11 @@ impl<'a> UniswapPoolStorage<'a> {
 
     pub fn slot0(&self) -> Slot0Struct {
         let slot0_slot = utils::left_pad_from_bigint(&BigInt::from(0));
-        return Slot0Struct::new(self.filtered_changes(), slot0_slot);
+        return Slot0Struct::new(self.filtered_changes(), slot0_slot, self.layout);
     }
 
     pub fn ticks(&self, tick_idx: &BigInt) -> TickStruct {
-        let ticks_slot = utils::left_pad_from_bigint(&BigInt::from(5));
+        let ticks_slot = utils::left_pad_from_bigint(&self.layout.slot(5));
         let ticker_struct_slot = utils::calc_map_slot(&utils::left_pad_from_bigint(&tick_idx), &ticks_slot);
         return TickStruct::new(self.filtered_changes(), ticker_struct_slot);
     }
@@ -102,13 +130,15 @@ impl<'a> UniswapPoolStorage<'a> {
 pub struct Slot0Struct<'a> {
     pub storage_changes: Vec<&'a StorageChange>,
     pub struct_slot: [u8; 32],
+    pub layout: PoolLayout,
 }
 
 impl<'a> Slot0Struct<'a> {
-    pub fn new(storage_changes: Vec<&'a StorageChange>, struct_slot: [u8; 32]) -> Slot0Struct<'a> {
+    pub fn new(storage_changes: Vec<&'a StorageChange>, struct_slot: [u8; 32], layout: PoolLayout) -> Slot0Struct<'a> {
         return Self {
             struct_slot: struct_slot,
             storage_changes: storage_changes,
+            layout: layout,
         };
     }
 
@@ -220,9 +250,10 @@ impl<'a> Slot0Struct<'a> {
     // the current protocol fee as a percentage of the swap fee taken on withdrawal
     // represented as an integer denominator (1/x)%
     pub fn fee_protocol(&self) -> Option<(BigInt, BigInt)> {
-        let slot = BigInt::zero();
-        let offset = 29;
-        let number_of_bytes = 1;
+        let (slot, offset, number_of_bytes) = match self.layout {
+            PoolLayout::UniswapV3 => (BigInt::zero(), 29, 1),
+            PoolLayout::PancakeV3 => (BigInt::one(), 0, 4),
+        };
         // &left_pad_from_bigint(&slot0_slot)
 
         let slot_key = utils::calc_struct_slot(&self.struct_slot, slot);
@@ -241,9 +272,10 @@ impl<'a> Slot0Struct<'a> {
 
     // whether the pool is locked
     pub fn unlocked(&self) -> Option<(bool, bool)> {
-        let slot = BigInt::zero();
-        let offset = 30;
-        let number_of_bytes = 1;
+        let (slot, offset, number_of_bytes) = match self.layout {
+            PoolLayout::UniswapV3 => (BigInt::zero(), 30, 1),
+            PoolLayout::PancakeV3 => (BigInt::one(), 4, 1),
+        };
         // &left_pad_from_bigint(&slot0_slot)
 
         let slot_key = utils::calc_struct_slot(&self.struct_slot, slot);
@@ -328,7 +360,7 @@ impl<'a> TickStruct<'a> {
 
 #[cfg(test)]
 mod tests {
-    use crate::storage::uniswap_v3_pool::UniswapPoolStorage;
+    use crate::storage::uniswap_v3_pool::{PoolLayout, UniswapPoolStorage};
     use crate::storage::utils;
     use std::ops::Add;
     use std::str::FromStr;
@@ -569,6 +601,45 @@ mod tests {
         );
     }
 
+    #[test]
+    fn pancake_v3_layout() {
+        let storage_changes = vec![
+            StorageChange {
+                address: hex!("779dfffb81550bf503c19d52b1e91e9251234faa").to_vec(),
+                key: hex!("0000000000000000000000000000000000000000000000000000000000000001").to_vec(),
+                old_value: hex!("0000000000000000000000000000000000000000000000000000000000000000").to_vec(),
+                new_value: hex!("0000000000000000000000000000000000000000000000000000000100000000").to_vec(),
+                ordinal: 10,
+            },
+            StorageChange {
+                address: hex!("779dfffb81550bf503c19d52b1e91e9251234faa").to_vec(),
+                key: hex!("0000000000000000000000000000000000000000000000000000000000000005").to_vec(),
+                old_value: hex!("000000000000000000000000000000000000000000000051eb0c7b51a54cf028").to_vec(),
+                new_value: hex!("0000000000000000000000000000000000000000000000000000000000000000").to_vec(),
+                ordinal: 287,
+            },
+        ];
+
+        let storage = UniswapPoolStorage::with_layout(
+            &storage_changes,
+            &hex!("779dfffb81550bf503c19d52b1e91e9251234faa").to_vec(),
+            PoolLayout::PancakeV3,
+        );
+        assert_eq!(
+            Some((BigInt::from_str("1511123317859703124008").unwrap(), BigInt::from(0))),
+            storage.liquidity()
+        );
+        assert_eq!(Some((false, true)), storage.slot0().unlocked());
+        // the second slot of slot0 is not the first fee growth under this layout
+        assert_eq!(None, storage.fee_growth_global0x128());
+
+        let uniswap_storage = UniswapPoolStorage::new(
+            &storage_changes,
+            &hex!("779dfffb81550bf503c19d52b1e91e9251234faa").to_vec(),
+        );
+        assert_eq!(None, uniswap_storage.liquidity());
+    }
+
     #[test]
     fn slot_calc() {
         // slot of ticks map
@@ -0,0 +1,79 @@
+/// Lowest tick of the pools, the tick search stops at its word.
+pub const MIN_TICK: i32 = -887272;
+
+/// Word of the tick bitmap of a pool holding a tick, each word holding 256 ticks of the spacing as
+/// in the `tickBitmap` of the pools.
+pub fn word_position(tick: i32, tick_spacing: i32) -> i32 {
+    tick.div_euclid(tick_spacing) >> 8
+}
+
+/// Nearest initialized tick at or below `tick`, the lower tick of the liquidity range the pool
+/// price is in. Only the words with initialized ticks are walked through, `word_has_ticks` and
+/// `is_initialized` reading the word counts of `store_tick_words` and the `liquidityGross` of the
+/// ticks.
+pub fn nearest_initialized_tick(
+    tick: i32,
+    tick_spacing: i32,
+    word_has_ticks: impl Fn(i32) -> bool,
+    is_initialized: impl Fn(i32) -> bool,
+) -> Option<i32> {
+    let min_compressed = MIN_TICK.div_euclid(tick_spacing);
+    let compressed = tick.div_euclid(tick_spacing);
+    let current_word = compressed >> 8;
+
+    for word in ((min_compressed >> 8)..=current_word).rev() {
+        if !word_has_ticks(word) {
+            continue;
+        }
+        let highest = match word == current_word {
+            true => compressed,
+            false => (word << 8) + 255,
+        };
+        let lowest = (word << 8).max(min_compressed);
+        if let Some(compressed) = (lowest..=highest)
+            .rev()
+            .find(|compressed| is_initialized(compressed * tick_spacing))
+        {
+            return Some(compressed * tick_spacing);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn search(tick: i32, tick_spacing: i32, initialized: &[i32]) -> Option<i32> {
+        nearest_initialized_tick(
+            tick,
+            tick_spacing,
+            |word| initialized.iter().any(|t| word_position(*t, tick_spacing) == word),
+            |t| initialized.contains(&t),
+        )
+    }
+
+    #[test]
+    fn test_word_position() {
+        assert_eq!(0, word_position(0, 1));
+        assert_eq!(1, word_position(256, 1));
+        assert_eq!(-1, word_position(-1, 1));
+        assert_eq!(-1, word_position(-60, 60));
+        assert_eq!(0, word_position(15_300, 60));
+        assert_eq!(1, word_position(15_360, 60));
+    }
+
+    #[test]
+    fn test_nearest_initialized_tick() {
+        // the tick starting the spacing the price is in
+        assert_eq!(Some(-60), search(-1, 60, &[-60, 0, 60]));
+        assert_eq!(Some(0), search(59, 60, &[-60, 0, 60]));
+        // an uninitialized spacing is credited to the lower end of the liquidity range
+        assert_eq!(Some(-120), search(70, 60, &[-120, 600]));
+        // across words
+        assert_eq!(Some(-200_040), search(200_000, 60, &[-200_040, 300_000]));
+        assert_eq!(Some(255), search(600, 1, &[255, 1000]));
+        assert_eq!(None, search(-100, 10, &[0, 10]));
+        assert_eq!(None, search(0, 1, &[]));
+    }
+}
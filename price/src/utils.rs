@@ -1,8 +1,10 @@
+use crate::config::Config;
 use crate::ethpb::v2::TransactionTrace;
 use crate::pb::uniswap::events;
 use crate::pb::AdjustedAmounts;
+use crate::storage::uniswap_v3_pool::PoolLayout;
 use crate::uniswap::events::Transaction;
-use crate::{storage, Erc20Token, StorageChange, WHITELIST_TOKENS};
+use crate::{storage, Erc20Token, StorageChange};
 use std::ops::{Add, Mul};
 use std::string::ToString;
 use substreams::prelude::StoreGetBigDecimal;
@@ -10,11 +12,7 @@ use substreams::scalar::{BigDecimal, BigInt};
 use substreams::store::StoreGet;
 use substreams::{hex, key, log, Hex};
 
-pub const UNISWAP_V3_FACTORY: [u8; 20] = hex!("1f98431c8ad98523631ae4a59f267346ea31f984");
-
 pub const ZERO_ADDRESS: [u8; 20] = hex!("0000000000000000000000000000000000000000");
-pub const NON_FUNGIBLE_POSITION_MANAGER: [u8; 20] = hex!("c36442b4a4522e871399cd717abdd847ab11fe88");
-pub const ERROR_POOL: [u8; 20] = hex!("8fe8d9bb8eeba3ed688069c3d6b556c9ca258248");
 
 const DGD_TOKEN_ADDRESS: [u8; 20] = hex!("e0b7927c4af23765cb51314a0e0521a9645f0e2a");
 const AAVE_TOKEN_ADDRESS: [u8; 20] = hex!("7fc66500c84a76ad7e9c93437bfc5ac33e2ddae9");
@@ -83,10 +81,11 @@ pub fn extract_pool_fee_growth_global_updates(
     log_ordinal: u64,
     pool_address: &Vec<u8>,
     storage_changes: &Vec<StorageChange>,
+    layout: PoolLayout,
 ) -> Vec<events::FeeGrowthGlobal> {
     let mut fee_growth_global = vec![];
 
-    let storage = storage::uniswap_v3_pool::UniswapPoolStorage::new(storage_changes, pool_address);
+    let storage = storage::uniswap_v3_pool::UniswapPoolStorage::with_layout(storage_changes, pool_address, layout);
 
     if let Some((_, new_value)) = storage.fee_growth_global0x128() {
         fee_growth_global.push(events::FeeGrowthGlobal {
@@ -134,6 +133,7 @@ pub fn get_tracked_amount_usd(
     amount0_abs: &BigDecimal,
     amount1_abs: &BigDecimal,
     eth_price_in_usd: &BigDecimal,
+    config: &Config,
 ) -> BigDecimal {
     let price0_usd = token0_derived_eth_price.clone().mul(eth_price_in_usd.clone());
     let price1_usd = token1_derived_eth_price.clone().mul(eth_price_in_usd.clone());
@@ -142,7 +142,7 @@ pub fn get_tracked_amount_usd(
     log::info!("price1_usd: {}", price1_usd);
 
     // both are whitelist tokens, return sum of both amounts
-    if WHITELIST_TOKENS.contains(&token0_id.as_str()) && WHITELIST_TOKENS.contains(&token1_id.as_str()) {
+    if config.is_whitelisted(token0_id) && config.is_whitelisted(token1_id) {
         return amount0_abs
             .clone()
             .mul(price0_usd)
@@ -150,12 +150,12 @@ pub fn get_tracked_amount_usd(
     }
 
     // take double value of the whitelisted token amount
-    if WHITELIST_TOKENS.contains(&token0_id.as_str()) && !WHITELIST_TOKENS.contains(&token1_id.as_str()) {
+    if config.is_whitelisted(token0_id) && !config.is_whitelisted(token1_id) {
         return amount0_abs.clone().mul(price0_usd).mul(BigDecimal::from(2 as i32));
     }
 
     // take double value of the whitelisted token amount
-    if !WHITELIST_TOKENS.contains(&token0_id.as_str()) && WHITELIST_TOKENS.contains(&token1_id.as_str()) {
+    if !config.is_whitelisted(token0_id) && config.is_whitelisted(token1_id) {
         return amount1_abs.clone().mul(price1_usd).mul(BigDecimal::from(2 as i32));
     }
 
@@ -171,6 +171,7 @@ pub fn get_adjusted_amounts(
     token0_derived_eth_price: &BigDecimal,
     token1_derived_eth_price: &BigDecimal,
     bundle_eth_price_usd: &BigDecimal,
+    config: &Config,
 ) -> AdjustedAmounts {
     log::info!("token0_addr {:}", token0_addr);
     log::info!("token1_addr {:}", token1_addr);
@@ -193,18 +194,18 @@ pub fn get_adjusted_amounts(
         .mul(token0_derived_eth_price.clone())
         .add(token1_amount.clone().mul(token1_derived_eth_price.clone()));
 
-    if WHITELIST_TOKENS.contains(&token0_addr.as_str()) && WHITELIST_TOKENS.contains(&token1_addr.as_str()) {
+    if config.is_whitelisted(token0_addr) && config.is_whitelisted(token1_addr) {
         eth = eth_untracked.clone()
     }
 
-    if WHITELIST_TOKENS.contains(&token0_addr.as_str()) && !WHITELIST_TOKENS.contains(&token1_addr.as_str()) {
+    if config.is_whitelisted(token0_addr) && !config.is_whitelisted(token1_addr) {
         eth = token0_amount
             .clone()
             .mul(token0_derived_eth_price.clone())
             .mul(BigDecimal::from(2 as i32));
     }
 
-    if !WHITELIST_TOKENS.contains(&token0_addr.as_str()) && WHITELIST_TOKENS.contains(&token1_addr.as_str()) {
+    if !config.is_whitelisted(token0_addr) && config.is_whitelisted(token1_addr) {
         eth = token1_amount
             .clone()
             .mul(token1_derived_eth_price.clone())
@@ -0,0 +1,102 @@
+use substreams::hex;
+use substreams::scalar::BigInt;
+
+/// `PairCreated(address,address,address,uint256)`
+const PAIR_CREATED_TOPIC: [u8; 32] = hex!("0d3648bd0f6ba80134a33ba9275ac585d9d315f0ad8355cddefde31afa28d0e9");
+/// `Swap(address,uint256,uint256,uint256,uint256,address)`
+const SWAP_TOPIC: [u8; 32] = hex!("d78ad95fa46c994b6551d0da85fc275fe613ce37657fb8d5e3d130840159d822");
+
+pub struct PairCreated {
+    pub token0: Vec<u8>,
+    pub token1: Vec<u8>,
+    pub pair: Vec<u8>,
+}
+
+/// Raw amounts of a pair swap, one of the in and out amounts of a token is usually zero.
+pub struct Swap {
+    pub amount0_in: BigInt,
+    pub amount1_in: BigInt,
+    pub amount0_out: BigInt,
+    pub amount1_out: BigInt,
+}
+
+pub fn decode_pair_created(topics: &[Vec<u8>], data: &[u8]) -> Option<PairCreated> {
+    if topics.len() != 3 || topics[0] != PAIR_CREATED_TOPIC || data.len() < 64 {
+        return None;
+    }
+    Some(PairCreated {
+        token0: topics[1][12..32].to_vec(),
+        token1: topics[2][12..32].to_vec(),
+        pair: data[12..32].to_vec(),
+    })
+}
+
+pub fn decode_swap(topics: &[Vec<u8>], data: &[u8]) -> Option<Swap> {
+    if topics.first()? != &SWAP_TOPIC || data.len() < 128 {
+        return None;
+    }
+    Some(Swap {
+        amount0_in: word(data, 0),
+        amount1_in: word(data, 1),
+        amount0_out: word(data, 2),
+        amount1_out: word(data, 3),
+    })
+}
+
+fn word(data: &[u8], index: usize) -> BigInt {
+    BigInt::from_unsigned_bytes_be(&data[index * 32..(index + 1) * 32])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(words: &[u64]) -> Vec<u8> {
+        words
+            .iter()
+            .flat_map(|value| {
+                let mut word = vec![0u8; 24];
+                word.extend_from_slice(&value.to_be_bytes());
+                word
+            })
+            .collect()
+    }
+
+    fn address_topic(address: &[u8]) -> Vec<u8> {
+        let mut topic = vec![0u8; 12];
+        topic.extend_from_slice(address);
+        topic
+    }
+
+    #[test]
+    fn test_decode_pair_created() {
+        let token0 = hex!("6b175474e89094c44da98b954eedeac495271d0f");
+        let token1 = hex!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+        let pair = hex!("a478c2975ab1ea89e8196811f51a7b7ade33eb11");
+        let topics = vec![
+            PAIR_CREATED_TOPIC.to_vec(),
+            address_topic(&token0),
+            address_topic(&token1),
+        ];
+        let mut data = address_topic(&pair);
+        data.extend(encode(&[1]));
+
+        let created = decode_pair_created(&topics, &data).unwrap();
+        assert_eq!(token0.to_vec(), created.token0);
+        assert_eq!(token1.to_vec(), created.token1);
+        assert_eq!(pair.to_vec(), created.pair);
+        assert!(decode_pair_created(&topics[..1], &data).is_none());
+    }
+
+    #[test]
+    fn test_decode_swap() {
+        let swap = decode_swap(&[SWAP_TOPIC.to_vec()], &encode(&[0, 2000, 1, 0])).unwrap();
+        assert_eq!(BigInt::zero(), swap.amount0_in);
+        assert_eq!(BigInt::from(2000), swap.amount1_in);
+        assert_eq!(BigInt::one(), swap.amount0_out);
+        assert_eq!(BigInt::zero(), swap.amount1_out);
+
+        assert!(decode_swap(&[PAIR_CREATED_TOPIC.to_vec()], &encode(&[0, 2000, 1, 0])).is_none());
+        assert!(decode_swap(&[SWAP_TOPIC.to_vec()], &encode(&[0, 2000])).is_none());
+    }
+}
@@ -0,0 +1,216 @@
+use substreams::hex;
+use substreams::scalar::BigInt;
+
+/// `Initialize(bytes32,address,address,uint24,int24,address,uint160,int24)`
+const INITIALIZE_TOPIC: [u8; 32] = hex!("dd466e674ea557f56295e2d0218a125ea4b4f0f6f3307b95f85e6110838d6438");
+/// `Swap(bytes32,address,int128,int128,uint160,uint128,int24,uint24)`
+const SWAP_TOPIC: [u8; 32] = hex!("40e9cecb9f5f1f1c5b9c97dec2917b7ee92e57ba5563708daca94dd84ad7112f");
+/// `ModifyLiquidity(bytes32,address,int24,int24,int256,bytes32)`
+const MODIFY_LIQUIDITY_TOPIC: [u8; 32] = hex!("f208f4912782fd25c7f114ca3723a2d5dd6f3bcc3ac8db5af63baa85f711d5ec");
+
+/// Fee of the pools whose hooks set the fee of every swap, the applied fee is in the `Swap`.
+pub const DYNAMIC_FEE_FLAG: u64 = 0x800000;
+
+pub struct Initialize {
+    pub id: Vec<u8>,
+    pub currency0: Vec<u8>,
+    pub currency1: Vec<u8>,
+    pub fee: u64,
+    pub tick_spacing: BigInt,
+    pub hooks: Vec<u8>,
+    pub sqrt_price_x96: BigInt,
+    pub tick: BigInt,
+}
+
+impl Initialize {
+    pub fn dynamic_fee(&self) -> bool {
+        self.fee == DYNAMIC_FEE_FLAG
+    }
+}
+
+/// Swap of a pool, the amounts are the balance deltas of the swapper, negative when paid to the pool.
+pub struct Swap {
+    pub id: Vec<u8>,
+    pub sender: Vec<u8>,
+    pub amount0: BigInt,
+    pub amount1: BigInt,
+    pub sqrt_price_x96: BigInt,
+    pub liquidity: BigInt,
+    pub tick: BigInt,
+    pub fee: u64,
+}
+
+/// Liquidity added to or, with a negative delta, removed from a position of a pool.
+pub struct ModifyLiquidity {
+    pub id: Vec<u8>,
+    pub sender: Vec<u8>,
+    pub tick_lower: BigInt,
+    pub tick_upper: BigInt,
+    pub liquidity_delta: BigInt,
+}
+
+/// The zero address is the currency of the native ether, and the hooks of the pools without hooks.
+pub fn is_zero_address(address: &[u8]) -> bool {
+    address.iter().all(|byte| *byte == 0)
+}
+
+pub fn decode_initialize(topics: &[Vec<u8>], data: &[u8]) -> Option<Initialize> {
+    if topics.len() != 4 || topics[0] != INITIALIZE_TOPIC || data.len() < 5 * 32 {
+        return None;
+    }
+    Some(Initialize {
+        id: topics[1].clone(),
+        currency0: topics[2][12..32].to_vec(),
+        currency1: topics[3][12..32].to_vec(),
+        fee: word(data, 0).to_u64(),
+        tick_spacing: signed_word(data, 1),
+        hooks: data[2 * 32 + 12..3 * 32].to_vec(),
+        sqrt_price_x96: word(data, 3),
+        tick: signed_word(data, 4),
+    })
+}
+
+pub fn decode_swap(topics: &[Vec<u8>], data: &[u8]) -> Option<Swap> {
+    if topics.len() != 3 || topics[0] != SWAP_TOPIC || data.len() < 6 * 32 {
+        return None;
+    }
+    Some(Swap {
+        id: topics[1].clone(),
+        sender: topics[2][12..32].to_vec(),
+        amount0: signed_word(data, 0),
+        amount1: signed_word(data, 1),
+        sqrt_price_x96: word(data, 2),
+        liquidity: word(data, 3),
+        tick: signed_word(data, 4),
+        fee: word(data, 5).to_u64(),
+    })
+}
+
+pub fn decode_modify_liquidity(topics: &[Vec<u8>], data: &[u8]) -> Option<ModifyLiquidity> {
+    if topics.len() != 3 || topics[0] != MODIFY_LIQUIDITY_TOPIC || data.len() < 4 * 32 {
+        return None;
+    }
+    Some(ModifyLiquidity {
+        id: topics[1].clone(),
+        sender: topics[2][12..32].to_vec(),
+        tick_lower: signed_word(data, 0),
+        tick_upper: signed_word(data, 1),
+        liquidity_delta: signed_word(data, 2),
+    })
+}
+
+/// Token amounts moved by the liquidity of a `modifyLiquidity` call, read from its returned
+/// `(callerDelta, feesAccrued)` balance deltas with the accrued fees taken out. The amounts are
+/// positive, whether the liquidity was added or removed.
+pub fn decode_modify_liquidity_amounts(return_data: &[u8]) -> Option<(BigInt, BigInt)> {
+    if return_data.len() < 64 {
+        return None;
+    }
+    let (caller0, caller1) = balance_delta(&return_data[0..32]);
+    let (fees0, fees1) = balance_delta(&return_data[32..64]);
+    Some(((caller0 - fees0).absolute(), (caller1 - fees1).absolute()))
+}
+
+/// A `BalanceDelta` packs the amount of currency0 in its upper 128 bits and currency1 in the lower.
+fn balance_delta(word: &[u8]) -> (BigInt, BigInt) {
+    (
+        BigInt::from_signed_bytes_be(&word[0..16]),
+        BigInt::from_signed_bytes_be(&word[16..32]),
+    )
+}
+
+fn word(data: &[u8], index: usize) -> BigInt {
+    BigInt::from_unsigned_bytes_be(&data[index * 32..(index + 1) * 32])
+}
+
+fn signed_word(data: &[u8], index: usize) -> BigInt {
+    BigInt::from_signed_bytes_be(&data[index * 32..(index + 1) * 32])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(words: &[i64]) -> Vec<u8> {
+        words
+            .iter()
+            .flat_map(|value| {
+                let fill = if *value < 0 { 0xff } else { 0 };
+                let mut word = vec![fill; 24];
+                word.extend_from_slice(&value.to_be_bytes());
+                word
+            })
+            .collect()
+    }
+
+    fn address_topic(address: &[u8]) -> Vec<u8> {
+        let mut topic = vec![0u8; 12];
+        topic.extend_from_slice(address);
+        topic
+    }
+
+    #[test]
+    fn test_decode_initialize() {
+        let id = hex!("21c67e77068de97969ba93d4aab21826d33ca12bb9f565d8496e8fda8a82ca27");
+        let usdc = hex!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let hooks = hex!("0000000000000000000000000000000000000a00");
+        let topics = vec![
+            INITIALIZE_TOPIC.to_vec(),
+            id.to_vec(),
+            address_topic(&[0u8; 20]),
+            address_topic(&usdc),
+        ];
+        let mut data = encode(&[DYNAMIC_FEE_FLAG as i64, 60]);
+        data.extend(address_topic(&hooks));
+        data.extend(encode(&[79_228_162_514, -195_000]));
+
+        let initialize = decode_initialize(&topics, &data).unwrap();
+        assert_eq!(id.to_vec(), initialize.id);
+        assert_eq!(vec![0u8; 20], initialize.currency0);
+        assert_eq!(usdc.to_vec(), initialize.currency1);
+        assert!(initialize.dynamic_fee());
+        assert!(is_zero_address(&initialize.currency0));
+        assert!(!is_zero_address(&initialize.currency1));
+        assert_eq!(BigInt::from(60), initialize.tick_spacing);
+        assert_eq!(hooks.to_vec(), initialize.hooks);
+        assert_eq!(BigInt::from(-195_000), initialize.tick);
+        assert!(decode_initialize(&topics[..3], &data).is_none());
+    }
+
+    #[test]
+    fn test_decode_swap_and_modify_liquidity() {
+        let id = hex!("21c67e77068de97969ba93d4aab21826d33ca12bb9f565d8496e8fda8a82ca27");
+        let sender = address_topic(&hex!("66a9893cc07d91d95644aedd05d03f95e1dba8af"));
+        let topics = vec![SWAP_TOPIC.to_vec(), id.to_vec(), sender.clone()];
+        let swap = decode_swap(
+            &topics,
+            &encode(&[-1_000_000, 400, 79_228_162_514, 10_000, -195_001, 3000]),
+        )
+        .unwrap();
+        assert_eq!(BigInt::from(-1_000_000), swap.amount0);
+        assert_eq!(BigInt::from(400), swap.amount1);
+        assert_eq!(BigInt::from(10_000), swap.liquidity);
+        assert_eq!(3000, swap.fee);
+
+        let topics = vec![MODIFY_LIQUIDITY_TOPIC.to_vec(), id.to_vec(), sender];
+        let modify = decode_modify_liquidity(&topics, &encode(&[-60, 60, -5000, 0])).unwrap();
+        assert_eq!(BigInt::from(-60), modify.tick_lower);
+        assert_eq!(BigInt::from(-5000), modify.liquidity_delta);
+        assert!(decode_swap(&topics, &encode(&[-60, 60, -5000, 0, 0, 0])).is_none());
+    }
+
+    #[test]
+    fn test_decode_modify_liquidity_amounts() {
+        // callerDelta (-990, 20) with (10, 20) of accrued fees
+        let mut caller = (-990i128).to_be_bytes().to_vec();
+        caller.extend_from_slice(&20i128.to_be_bytes());
+        let mut fees = 10i128.to_be_bytes().to_vec();
+        fees.extend_from_slice(&20i128.to_be_bytes());
+        let return_data = [caller, fees].concat();
+
+        let (amount0, amount1) = decode_modify_liquidity_amounts(&return_data).unwrap();
+        assert_eq!(BigInt::from(1000), amount0);
+        assert_eq!(BigInt::zero(), amount1);
+        assert!(decode_modify_liquidity_amounts(&return_data[..32]).is_none());
+    }
+}
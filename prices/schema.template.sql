@@ -0,0 +1,142 @@
+CREATE DATABASE IF NOT EXISTS {{DATABASE}};
+
+CREATE TABLE IF NOT EXISTS {{DATABASE}}.cursors
+(
+    id         String,
+	cursor     String,
+	block_num  Int64,
+	block_id   String
+) Engine = ReplacingMergeTree() ORDER BY id;
+
+CREATE TABLE IF NOT EXISTS {{DATABASE}}.tokens (
+  blockchain String, 
+  contract_address FixedString(40),
+  name String,
+  symbol String,
+  decimals UInt32,
+  total_supply UInt256,
+  first_block_number UInt64,
+  first_pool String,
+  block_number UInt64,
+
+  primary key (blockchain, contract_address)
+) Engine = ReplacingMergeTree(block_number);
+
+CREATE TABLE IF NOT EXISTS {{DATABASE}}."values" (
+  blockchain String, 
+  hour DateTime,
+  contract_address FixedString(40),
+  price Float64,
+  open Float64,
+  high Float64,
+  low Float64,
+  close Float64,
+  open_eth Float64,
+  high_eth Float64,
+  low_eth Float64,
+  close_eth Float64,
+  volume_usd Float64,
+  source_pool String,
+  source_tvl_eth Float64,
+  source_tvl_usd Float64,
+  swap_count UInt64,
+
+  primary key (blockchain, hour, contract_address)
+) Engine = MergeTree();
+
+CREATE TABLE IF NOT EXISTS {{DATABASE}}.daily_values (
+  blockchain String,
+  day DateTime,
+  contract_address FixedString(40),
+  price Float64,
+  open Float64,
+  high Float64,
+  low Float64,
+  close Float64,
+  open_eth Float64,
+  high_eth Float64,
+  low_eth Float64,
+  close_eth Float64,
+  volume_usd Float64,
+  source_pool String,
+  source_tvl_eth Float64,
+  source_tvl_usd Float64,
+  swap_count UInt64,
+
+  primary key (blockchain, day, contract_address)
+) Engine = MergeTree();
+
+CREATE TABLE IF NOT EXISTS {{DATABASE}}.latest_prices (
+  blockchain String,
+  contract_address FixedString(40),
+  price_usd Float64,
+  price_eth Float64,
+  block_number UInt64,
+  timestamp DateTime,
+
+  primary key (blockchain, contract_address)
+) Engine = ReplacingMergeTree(block_number);
+
+CREATE TABLE IF NOT EXISTS {{DATABASE}}.dex_prices (
+  blockchain String,
+  period String,
+  time DateTime,
+  contract_address FixedString(40),
+  price_usd Float64,
+  volume_usd Float64,
+  source_count UInt64,
+
+  primary key (blockchain, period, time, contract_address)
+) Engine = MergeTree();
+
+CREATE TABLE IF NOT EXISTS {{DATABASE}}.dex_price_sources (
+  blockchain String,
+  period String,
+  time DateTime,
+  contract_address FixedString(40),
+  source String,
+  price_usd Float64,
+  volume_usd Float64,
+  weight Float64,
+
+  primary key (blockchain, period, time, contract_address, source)
+) Engine = MergeTree();
+
+CREATE TABLE IF NOT EXISTS {{DATABASE}}.reference_prices (
+  blockchain String,
+  feed FixedString(40),
+  round_id String,
+  contract_address FixedString(40),
+  price_usd Float64,
+  updated_at DateTime,
+  block_number UInt64,
+  transaction_hash FixedString(64),
+
+  primary key (blockchain, feed, round_id)
+) Engine = MergeTree();
+
+CREATE TABLE IF NOT EXISTS {{DATABASE}}.price_deviations (
+  blockchain String,
+  feed FixedString(40),
+  round_id String,
+  trigger String,
+  contract_address FixedString(40),
+  reference_price_usd Float64,
+  dex_price_usd Float64,
+  deviation_bps Float64,
+  updated_at DateTime,
+  block_number UInt64,
+
+  primary key (blockchain, feed, round_id)
+) Engine = MergeTree();
+
+CREATE TABLE IF NOT EXISTS {{DATABASE}}.stablecoin_pegs (
+  blockchain String,
+  hour DateTime,
+  contract_address FixedString(40),
+  price_usd Float64,
+  peg_ratio Float64,
+  depegged Bool,
+
+  primary key (blockchain, hour, contract_address)
+) Engine = MergeTree();
@@ -0,0 +1,153 @@
+use std::collections::{BTreeMap, HashMap};
+
+use substreams::key::{first_segment, last_segment, segment_at};
+use substreams::pb::substreams::store_delta::Operation;
+use substreams::scalar::BigDecimal;
+use substreams::store::{DeltaBigDecimal, DeltaExt, DeltaString, Deltas};
+
+/// Price window of the `price` stores and the table its candles are written to.
+pub struct Window {
+    pub table: &'static str,
+    pub time_column: &'static str,
+    pub seconds: u64,
+    usd_prefix: &'static str,
+    eth_prefix: &'static str,
+    pool_prefix: &'static str,
+}
+
+pub const WINDOWS: [Window; 2] = [
+    Window {
+        table: "values",
+        time_column: "hour",
+        seconds: 3600,
+        usd_prefix: "TokenHourData",
+        eth_prefix: "TokenHourDataETH",
+        pool_prefix: "PoolHourData",
+    },
+    Window {
+        table: "daily_values",
+        time_column: "day",
+        seconds: 86400,
+        usd_prefix: "TokenDayData",
+        eth_prefix: "TokenDayDataETH",
+        pool_prefix: "PoolDayData",
+    },
+];
+
+#[derive(Default)]
+pub struct Candle {
+    pub open: Option<BigDecimal>,
+    pub high: Option<BigDecimal>,
+    pub low: Option<BigDecimal>,
+    pub close: Option<BigDecimal>,
+    pub open_eth: Option<BigDecimal>,
+    pub high_eth: Option<BigDecimal>,
+    pub low_eth: Option<BigDecimal>,
+    pub close_eth: Option<BigDecimal>,
+    pub volume_usd: Option<BigDecimal>,
+    /// Pool the close price was derived from, empty for stablecoins.
+    pub source_pool: String,
+    /// Swaps of the source pool during the window.
+    pub swap_count: u64,
+}
+
+/// Candles of the windows closed in this block, keyed by window index, time id and token address.
+///
+/// The `price` stores delete a window when the next one starts, the deleted values are its final
+/// close, open, low, high and volume, along with the pool the price came from.
+pub fn closed_candles(
+    eth_price_deltas: &Deltas<DeltaBigDecimal>,
+    price_sources_deltas: &Deltas<DeltaString>,
+    min_windows_deltas: &Deltas<DeltaBigDecimal>,
+    max_windows_deltas: &Deltas<DeltaBigDecimal>,
+    swaps_volume_deltas: &Deltas<DeltaBigDecimal>,
+) -> BTreeMap<(usize, u64, String), Candle> {
+    let mut candles: BTreeMap<(usize, u64, String), Candle> = BTreeMap::new();
+    let mut swap_counts: HashMap<(usize, u64, String), u64> = HashMap::new();
+    let deltas = eth_price_deltas
+        .deltas
+        .iter()
+        .chain(min_windows_deltas.deltas.iter())
+        .chain(max_windows_deltas.deltas.iter())
+        .chain(swaps_volume_deltas.deltas.iter());
+
+    for delta in deltas.operation_eq(Operation::Delete) {
+        let prefix = first_segment(&delta.key);
+        if last_segment(&delta.key) == "swapCount" {
+            if let Some((window, time_id)) = window_of(&delta.key, |window| window.pool_prefix) {
+                let pool_address = segment_at(&delta.key, 2).to_string();
+                let count = delta.old_value.to_string().parse::<u64>().unwrap_or(0);
+                swap_counts.insert((window, time_id, pool_address), count);
+            }
+            continue;
+        }
+        let (window, eth) = match WINDOWS.iter().enumerate().find_map(|(index, window)| {
+            if prefix == window.usd_prefix {
+                Some((index, false))
+            } else if prefix == window.eth_prefix {
+                Some((index, true))
+            } else {
+                None
+            }
+        }) {
+            Some(found) => found,
+            None => continue,
+        };
+        let time_id = match segment_at(&delta.key, 1).parse::<u64>() {
+            Ok(time_id) => time_id,
+            Err(_) => continue,
+        };
+        let token_address = segment_at(&delta.key, 2).to_string();
+        let candle = candles.entry((window, time_id, token_address)).or_default();
+
+        let value = Some(delta.old_value.clone());
+        // price keys have 3 segments, window aggregates add the field name as a 4th one
+        let field = if delta.key.matches(':').count() == 2 {
+            "close"
+        } else {
+            last_segment(&delta.key)
+        };
+        match (field, eth) {
+            ("close", false) => candle.close = value,
+            ("open", false) => candle.open = value,
+            ("high", false) => candle.high = value,
+            ("low", false) => candle.low = value,
+            ("volumeUSD", false) => candle.volume_usd = value,
+            ("close", true) => candle.close_eth = value,
+            ("open", true) => candle.open_eth = value,
+            ("high", true) => candle.high_eth = value,
+            ("low", true) => candle.low_eth = value,
+            _ => {}
+        }
+    }
+
+    for delta in price_sources_deltas.deltas.iter().operation_eq(Operation::Delete) {
+        if let Some((window, time_id)) = window_of(&delta.key, |window| window.usd_prefix) {
+            let token_address = segment_at(&delta.key, 2).to_string();
+            if let Some(candle) = candles.get_mut(&(window, time_id, token_address)) {
+                candle.source_pool = delta.old_value.clone();
+            }
+        }
+    }
+
+    candles.retain(|_, candle| candle.close.is_some());
+    for ((window, time_id, _), candle) in candles.iter_mut() {
+        candle.swap_count = swap_counts
+            .get(&(*window, *time_id, candle.source_pool.clone()))
+            .copied()
+            .unwrap_or(0);
+    }
+    candles
+}
+
+/// Window index and time id of a `TokenHourData:{time_id}:...` or `TokenDayData:{time_id}:...` key.
+pub fn token_window(key: &str) -> Option<(usize, u64)> {
+    window_of(key, |window| window.usd_prefix)
+}
+
+/// Window index and time id of a `{prefix}:{time_id}:...` key.
+fn window_of(key: &str, prefix: fn(&Window) -> &'static str) -> Option<(usize, u64)> {
+    let window = WINDOWS.iter().position(|window| prefix(window) == first_segment(key))?;
+    let time_id = segment_at(key, 1).parse::<u64>().ok()?;
+    Some((window, time_id))
+}
@@ -0,0 +1,78 @@
+use std::collections::BTreeMap;
+
+use substreams::key::{last_segment, segment_at};
+use substreams::pb::substreams::store_delta::Operation;
+use substreams::scalar::BigDecimal;
+use substreams::store::{DeltaBigDecimal, DeltaExt, Deltas};
+
+use crate::candles;
+
+/// Trades of a token on one source during a window.
+pub struct SourceVolume {
+    pub volume_usd: BigDecimal,
+    /// Sum of the trade prices weighted by their USD volume.
+    pub price_volume_usd: BigDecimal,
+}
+
+impl Default for SourceVolume {
+    fn default() -> Self {
+        SourceVolume {
+            volume_usd: BigDecimal::zero(),
+            price_volume_usd: BigDecimal::zero(),
+        }
+    }
+}
+
+impl SourceVolume {
+    pub fn price_usd(&self) -> BigDecimal {
+        if self.volume_usd.eq(&BigDecimal::zero()) {
+            return BigDecimal::zero();
+        }
+        self.price_volume_usd.clone() / self.volume_usd.clone()
+    }
+}
+
+/// Per source trades of the windows closed in this block, keyed by window index, time id and
+/// token address, then by source.
+///
+/// `store_dex_prices` deletes a window when the next one starts, the deleted values are its totals.
+pub fn closed_dex_volumes(
+    dex_prices_deltas: &Deltas<DeltaBigDecimal>,
+) -> BTreeMap<(usize, u64, String), BTreeMap<String, SourceVolume>> {
+    let mut volumes: BTreeMap<(usize, u64, String), BTreeMap<String, SourceVolume>> = BTreeMap::new();
+
+    for delta in dex_prices_deltas.deltas.iter().operation_eq(Operation::Delete) {
+        let (window, time_id) = match candles::token_window(&delta.key) {
+            Some(found) => found,
+            None => continue,
+        };
+        // {prefix}:{time_id}:{token}:{source}:{field}
+        if delta.key.matches(':').count() != 4 {
+            continue;
+        }
+        let token_address = segment_at(&delta.key, 2).to_string();
+        let source = segment_at(&delta.key, 3).to_string();
+        let volume = volumes
+            .entry((window, time_id, token_address))
+            .or_default()
+            .entry(source)
+            .or_default();
+        match last_segment(&delta.key) {
+            "volumeUSD" => volume.volume_usd = delta.old_value.clone(),
+            "priceVolumeUSD" => volume.price_volume_usd = delta.old_value.clone(),
+            _ => {}
+        }
+    }
+
+    volumes
+}
+
+/// Volume weighted price and total volume over all the sources.
+pub fn combined(sources: &BTreeMap<String, SourceVolume>) -> SourceVolume {
+    sources
+        .values()
+        .fold(SourceVolume::default(), |total, source| SourceVolume {
+            volume_usd: total.volume_usd + source.volume_usd.clone(),
+            price_volume_usd: total.price_volume_usd + source.price_volume_usd.clone(),
+        })
+}
@@ -0,0 +1,104 @@
+use std::collections::{BTreeMap, HashMap};
+
+use substreams::key::{first_segment, segment_at};
+use substreams::pb::substreams::store_delta::Operation;
+use substreams::scalar::BigDecimal;
+use substreams::store::{DeltaBigDecimal, DeltaExt, Deltas};
+use substreams_database_change::pb::database::{table_change, DatabaseChanges};
+
+/// Last USD and ETH prices of every token updated in this block, keyed by token address.
+pub fn latest_prices(price_deltas: &Deltas<DeltaBigDecimal>) -> BTreeMap<String, (BigDecimal, Option<BigDecimal>)> {
+    let mut prices: BTreeMap<String, (Option<BigDecimal>, Option<BigDecimal>)> = BTreeMap::new();
+    for delta in price_deltas.deltas.iter().operation_not_eq(Operation::Delete) {
+        let eth = match first_segment(&delta.key) {
+            "TokenHourData" => false,
+            "TokenHourDataETH" => true,
+            _ => continue,
+        };
+        let latest = prices.entry(segment_at(&delta.key, 2).to_string()).or_default();
+        if eth {
+            latest.1 = Some(delta.new_value.clone());
+        } else {
+            latest.0 = Some(delta.new_value.clone());
+        }
+    }
+    prices
+        .into_iter()
+        .filter_map(|(token_address, (price_usd, price_eth))| Some((token_address, (price_usd?, price_eth))))
+        .collect()
+}
+
+/// One `latest_prices` row per token and block. The block number is part of the key so the rows
+/// of consecutive blocks stay apart until the ReplacingMergeTree keeps the highest one.
+pub fn push_latest_prices(
+    database_changes: &mut DatabaseChanges,
+    blockchain: &str,
+    block_number: u64,
+    timestamp: i64,
+    price_deltas: &Deltas<DeltaBigDecimal>,
+) {
+    for (token_address, (price_usd, price_eth)) in latest_prices(price_deltas) {
+        let mut keys = HashMap::new();
+        keys.insert("blockchain".to_string(), blockchain.to_string());
+        keys.insert("contract_address".to_string(), token_address);
+        keys.insert("block_number".to_string(), block_number.to_string());
+
+        database_changes
+            .push_change_composite("latest_prices", keys, 0, table_change::Operation::Create)
+            .change("price_usd", (None, price_usd.to_string()))
+            .change("price_eth", (None, price_eth.unwrap_or(BigDecimal::zero()).to_string()))
+            .change("timestamp", (None, timestamp.to_string()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use substreams_database_change::pb::database::table_change::PrimaryKey;
+
+    fn price_delta(key: &str, price: u64) -> DeltaBigDecimal {
+        DeltaBigDecimal {
+            operation: Operation::Update,
+            ordinal: 1,
+            key: key.to_string(),
+            old_value: BigDecimal::zero(),
+            new_value: BigDecimal::from(price),
+        }
+    }
+
+    #[test]
+    fn test_latest_prices_of_consecutive_blocks() {
+        let token = "c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2";
+        let mut database_changes = DatabaseChanges::default();
+        for (block_number, price) in [(100u64, 2000u64), (101, 2010)] {
+            let deltas = Deltas {
+                deltas: vec![
+                    price_delta(&format!("TokenHourData:475000:{token}"), price - 1),
+                    price_delta(&format!("TokenHourData:475000:{token}"), price),
+                    price_delta(&format!("TokenHourDataETH:475000:{token}"), 1),
+                ],
+            };
+            push_latest_prices(&mut database_changes, "ethereum", block_number, 1_710_000_000, &deltas);
+        }
+
+        let block_numbers: Vec<String> = database_changes
+            .table_changes
+            .iter()
+            .map(|change| match change.primary_key.as_ref() {
+                Some(PrimaryKey::CompositePk(pk)) => {
+                    assert_eq!(token, pk.keys["contract_address"]);
+                    pk.keys["block_number"].clone()
+                }
+                _ => panic!("latest_prices has a composite key"),
+            })
+            .collect();
+        assert_eq!(vec!["100", "101"], block_numbers);
+        let prices: Vec<&str> = database_changes
+            .table_changes
+            .iter()
+            .map(|change| change.fields.iter().find(|field| field.name == "price_usd").unwrap())
+            .map(|field| field.new_value.as_str())
+            .collect();
+        assert_eq!(vec!["2000", "2010"], prices);
+    }
+}
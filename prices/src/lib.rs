@@ -1,123 +1,525 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
-use pb::{semiotic::price::Token, uniswap::types::v1::Pools};
+use params::{OracleParams, Params};
+use pb::semiotic::price::{OracleAnswer, OracleAnswers, Token, TokenOrigin};
+use pb::uniswap::types::v1::Pools;
 use substreams::key::segment_at;
-use substreams::store::DeltaProto;
-use substreams::store::{StoreNew, StoreSetIfNotExists, StoreSetIfNotExistsProto};
+use substreams::store::{DeltaExt, DeltaInt64, DeltaProto, DeltaString, StoreAdd, StoreAddInt64};
+use substreams::store::{
+    StoreGet, StoreGetBigDecimal, StoreGetProto, StoreGetString, StoreNew, StoreSet, StoreSetProto,
+};
+use substreams::store::{StoreSetIfNotExists, StoreSetIfNotExistsProto, StoreSetString};
+use substreams::scalar::BigDecimal;
+use substreams::Hex;
 use substreams::{
-    key::first_segment,
     pb::substreams::store_delta::Operation,
-    store::DeltaExt,
+    pb::substreams::Clock,
     store::{DeltaBigDecimal, Deltas},
 };
 use substreams_database_change::pb::database::table_change;
 use substreams_database_change::pb::database::DatabaseChanges;
+use substreams_ethereum::pb::eth::v2::Block;
 
+mod candles;
+mod dex;
+mod latest;
+mod oracle;
+mod params;
 mod pb;
-
-const BLOCKCHAIN: &str = "ethereum";
-const HOUR_IN_SECONDS: u64 = 3600;
+mod pegs;
+mod rpc;
+mod tokens;
 
 #[substreams::handlers::map]
 fn db_out(
-    price_deltas: Deltas<DeltaBigDecimal>, /* store_prices */
-    token_delta: Deltas<DeltaProto<Token>>,
+    params: String,
+    clock: Clock,
+    price_deltas: Deltas<DeltaBigDecimal>, /* store_eth_prices */
+    token_delta: Deltas<DeltaProto<Token>>,      /* store_token_info */
+    token_refreshes_delta: Deltas<DeltaProto<Token>>, /* store_token_refreshes */
+    token_origins_store: StoreGetProto<TokenOrigin>, /* store_token_origins */
+    min_windows_deltas: Deltas<DeltaBigDecimal>, /* store_min_windows */
+    max_windows_deltas: Deltas<DeltaBigDecimal>, /* store_max_windows */
+    swaps_volume_deltas: Deltas<DeltaBigDecimal>, /* store_swaps_volume */
+    price_sources_deltas: Deltas<DeltaString>,    /* store_price_sources */
+    derived_tvl_store: StoreGetBigDecimal,        /* store_derived_tvl */
+    dex_prices_deltas: Deltas<DeltaBigDecimal>,   /* store_dex_prices */
+    oracle_answers: OracleAnswers,                /* map_oracle_answers */
+    stablecoin_pegs_deltas: Deltas<DeltaBigDecimal>, /* store_stablecoin_pegs */
 ) -> Result<DatabaseChanges, substreams::errors::Error> {
-    db_out_inner(price_deltas, token_delta)
+    let params = Params::parse(&params)?;
+    db_out_inner(
+        &params.blockchain,
+        clock,
+        price_deltas,
+        token_delta,
+        token_refreshes_delta,
+        token_origins_store,
+        min_windows_deltas,
+        max_windows_deltas,
+        swaps_volume_deltas,
+        price_sources_deltas,
+        derived_tvl_store,
+        dex_prices_deltas,
+        oracle_answers,
+        stablecoin_pegs_deltas,
+    )
 }
 
 pub fn db_out_inner(
-    price_deltas: Deltas<DeltaBigDecimal>, /* store_prices */
-    token_delta: Deltas<DeltaProto<Token>>,
+    blockchain: &str,
+    clock: Clock,
+    price_deltas: Deltas<DeltaBigDecimal>, /* store_eth_prices */
+    token_delta: Deltas<DeltaProto<Token>>,      /* store_token_info */
+    token_refreshes_delta: Deltas<DeltaProto<Token>>, /* store_token_refreshes */
+    token_origins_store: StoreGetProto<TokenOrigin>, /* store_token_origins */
+    min_windows_deltas: Deltas<DeltaBigDecimal>, /* store_min_windows */
+    max_windows_deltas: Deltas<DeltaBigDecimal>, /* store_max_windows */
+    swaps_volume_deltas: Deltas<DeltaBigDecimal>, /* store_swaps_volume */
+    price_sources_deltas: Deltas<DeltaString>,    /* store_price_sources */
+    derived_tvl_store: StoreGetBigDecimal,        /* store_derived_tvl */
+    dex_prices_deltas: Deltas<DeltaBigDecimal>,   /* store_dex_prices */
+    oracle_answers: OracleAnswers,                /* map_oracle_answers */
+    stablecoin_pegs_deltas: Deltas<DeltaBigDecimal>, /* store_stablecoin_pegs */
 ) -> Result<DatabaseChanges, substreams::errors::Error> {
     let mut database_changes: DatabaseChanges = Default::default();
 
-    for delta in token_delta
-        .deltas
-        .iter()
-        .operation_eq(Operation::Create)
-    {
-        let token = delta.new_value.clone();
+    // Tokens are written again whenever their metadata changes, at most once per block. The block
+    // number is part of the key, the ReplacingMergeTree keeps the row with the highest one.
+    for (token_address, mut token) in tokens::changed_tokens(&token_delta, &token_refreshes_delta) {
+        if token.total_supply.is_empty() {
+            token.total_supply = "0".to_string();
+        }
+        let origin = token_origins_store.get_last(&token_address).unwrap_or_default();
         let mut keys = HashMap::new();
-        keys.insert("blockchain".to_string(), BLOCKCHAIN.to_string());
-        keys.insert("contract_address".to_string(), token.address.to_string());
-        keys.insert("name".to_string(), token.name);
-        keys.insert("symbol".to_string(), token.symbol);
+        keys.insert("blockchain".to_string(), blockchain.to_string());
+        keys.insert("contract_address".to_string(), token_address);
+        keys.insert("block_number".to_string(), clock.number.to_string());
 
         database_changes
-            .push_change_composite(
-                "tokens",
-                keys,
-                delta.ordinal,
-                table_change::Operation::Create,
-            )
-            .change("decimals", (None, token.decimals));
+            .push_change_composite("tokens", keys, 0, table_change::Operation::Create)
+            .change("name", (None, token.name))
+            .change("symbol", (None, token.symbol))
+            .change("decimals", (None, token.decimals))
+            .change("total_supply", (None, token.total_supply))
+            .change("first_block_number", (None, origin.block_number))
+            .change("first_pool", (None, origin.pool));
     }
 
-    for delta in price_deltas
-        .deltas
-        .iter()
-        .key_first_segment_in(vec!["TokenHourData"])
-        .operation_eq(Operation::Delete)
-    {
-        let (_, time_id, token_address) = pool_windows_id_fields(&delta.key);
+    let closed_candles = candles::closed_candles(
+        &price_deltas,
+        &price_sources_deltas,
+        &min_windows_deltas,
+        &max_windows_deltas,
+        &swaps_volume_deltas,
+    );
+    for ((window, time_id, token_address), candle) in closed_candles {
+        let window = &candles::WINDOWS[window];
+        let close = candle.close.unwrap_or(BigDecimal::zero());
+        let or_close = |value: Option<BigDecimal>| value.unwrap_or(close.clone()).to_string();
+        let or_zero = |value: Option<BigDecimal>| value.unwrap_or(BigDecimal::zero()).to_string();
+        // liquidity of the source pool when the window closed
+        let source_tvl = |currency: &str| {
+            if candle.source_pool.is_empty() {
+                return BigDecimal::zero().to_string();
+            }
+            or_zero(derived_tvl_store.get_last(format!("pool:{}:totalValueLocked{}", candle.source_pool, currency)))
+        };
+
+        let mut keys = HashMap::new();
+        keys.insert("blockchain".to_string(), blockchain.to_string());
+        keys.insert(
+            window.time_column.to_string(),
+            (time_id * window.seconds).to_string(),
+        );
+        keys.insert("contract_address".to_string(), token_address);
 
-        let time_id = time_id.parse::<u64>().unwrap() * HOUR_IN_SECONDS;
+        database_changes
+            .push_change_composite(window.table, keys, 0, table_change::Operation::Create)
+            .change("price", (None, close.to_string()))
+            .change("open", (None, or_close(candle.open)))
+            .change("high", (None, or_close(candle.high)))
+            .change("low", (None, or_close(candle.low)))
+            .change("close", (None, close.to_string()))
+            .change("open_eth", (None, or_zero(candle.open_eth)))
+            .change("high_eth", (None, or_zero(candle.high_eth)))
+            .change("low_eth", (None, or_zero(candle.low_eth)))
+            .change("close_eth", (None, or_zero(candle.close_eth)))
+            .change("volume_usd", (None, or_zero(candle.volume_usd)))
+            .change("source_pool", (None, candle.source_pool.clone()))
+            .change("source_tvl_eth", (None, source_tvl("ETH")))
+            .change("source_tvl_usd", (None, source_tvl("USD")))
+            .change("swap_count", (None, candle.swap_count));
+    }
+
+    for ((window, time_id, token_address), sources) in dex::closed_dex_volumes(&dex_prices_deltas) {
+        let window = &candles::WINDOWS[window];
+        let time = (time_id * window.seconds).to_string();
+        let total = dex::combined(&sources);
+        if total.volume_usd.eq(&BigDecimal::zero()) {
+            continue;
+        }
 
         let mut keys = HashMap::new();
-        keys.insert("blockchain".to_string(), BLOCKCHAIN.to_string());
-        keys.insert("hour".to_string(), time_id.to_string());
-        keys.insert("contract_address".to_string(), token_address.to_string());
+        keys.insert("blockchain".to_string(), blockchain.to_string());
+        keys.insert("period".to_string(), window.time_column.to_string());
+        keys.insert("time".to_string(), time.clone());
+        keys.insert("contract_address".to_string(), token_address.clone());
+        database_changes
+            .push_change_composite("dex_prices", keys, 0, table_change::Operation::Create)
+            .change("price_usd", (None, total.price_usd().to_string()))
+            .change("volume_usd", (None, total.volume_usd.to_string()))
+            .change("source_count", (None, sources.len() as u64));
 
+        for (source, volume) in sources {
+            let weight = volume.volume_usd.clone() / total.volume_usd.clone();
+            let mut keys = HashMap::new();
+            keys.insert("blockchain".to_string(), blockchain.to_string());
+            keys.insert("period".to_string(), window.time_column.to_string());
+            keys.insert("time".to_string(), time.clone());
+            keys.insert("contract_address".to_string(), token_address.clone());
+            keys.insert("source".to_string(), source);
+            database_changes
+                .push_change_composite("dex_price_sources", keys, 0, table_change::Operation::Create)
+                .change("price_usd", (None, volume.price_usd().to_string()))
+                .change("volume_usd", (None, volume.volume_usd.to_string()))
+                .change("weight", (None, weight.to_string()));
+        }
+    }
+
+    for answer in oracle_answers.answers {
+        if !answer.dex_triggered {
+            let mut keys = HashMap::new();
+            keys.insert("blockchain".to_string(), blockchain.to_string());
+            keys.insert("feed".to_string(), answer.feed.clone());
+            keys.insert("round_id".to_string(), answer.round_id.clone());
+            database_changes
+                .push_change_composite(
+                    "reference_prices",
+                    keys,
+                    answer.ordinal,
+                    table_change::Operation::Create,
+                )
+                .change("contract_address", (None, answer.token.clone()))
+                .change("price_usd", (None, answer.price_usd.clone()))
+                .change("updated_at", (None, answer.updated_at.to_string()))
+                .change("block_number", (None, clock.number))
+                .change("transaction_hash", (None, answer.transaction_hash.clone()));
+        }
+
+        if !answer.deviation_exceeded {
+            continue;
+        }
+        // a round deviates when it is answered and again on the DEX price changes that follow it
+        let trigger = if answer.dex_triggered { "dex" } else { "answer" };
+        let mut keys = HashMap::new();
+        keys.insert("blockchain".to_string(), blockchain.to_string());
+        keys.insert("feed".to_string(), answer.feed);
+        keys.insert("round_id".to_string(), answer.round_id);
+        keys.insert("trigger".to_string(), trigger.to_string());
+        keys.insert("block_number".to_string(), clock.number.to_string());
         database_changes
             .push_change_composite(
-                "values",
+                "price_deviations",
                 keys,
-                delta.ordinal,
+                answer.ordinal,
                 table_change::Operation::Create,
             )
-            .change("price", (None, delta.old_value.to_string()));
+            .change("contract_address", (None, answer.token))
+            .change("reference_price_usd", (None, answer.price_usd))
+            .change("dex_price_usd", (None, answer.dex_price_usd))
+            .change("deviation_bps", (None, answer.deviation_bps))
+            .change("updated_at", (None, answer.updated_at.to_string()));
+    }
+
+    for ((hour_id, stablecoin), peg) in pegs::closed_pegs(&stablecoin_pegs_deltas) {
+        let mut keys = HashMap::new();
+        keys.insert("blockchain".to_string(), blockchain.to_string());
+        keys.insert("hour".to_string(), (hour_id * 3600).to_string());
+        keys.insert("contract_address".to_string(), stablecoin);
+        database_changes
+            .push_change_composite("stablecoin_pegs", keys, 0, table_change::Operation::Create)
+            .change("price_usd", (None, peg.price_usd.to_string()))
+            .change("peg_ratio", (None, peg.peg_ratio.to_string()))
+            .change("depegged", (None, peg.depegged));
     }
+
+    // Every price update of the hourly window is also the latest price of the token
+    let timestamp = clock.timestamp.unwrap().seconds;
+    latest::push_latest_prices(
+        &mut database_changes,
+        blockchain,
+        clock.number,
+        timestamp,
+        &price_deltas,
+    );
     Ok(database_changes)
 }
 
+/// Blocks in which every Chainlink aggregator emitted an `AnswerUpdated`.
+#[substreams::handlers::store]
+fn store_answer_emitters(params: String, block: Block, store: StoreAddInt64) {
+    let params = OracleParams::parse(&params).unwrap();
+    if params.feeds.is_empty() {
+        return;
+    }
+    let mut emitters = BTreeSet::new();
+    for trx in block.transactions() {
+        for (log, _) in trx.logs_with_calls() {
+            if oracle::decode_answer_updated(&log.topics, &log.data).is_some() {
+                emitters.insert(Hex(&log.address).to_string());
+            }
+        }
+    }
+    for emitter in emitters {
+        store.add(0, emitter, 1);
+    }
+}
+
+/// Feed of the aggregators emitting answers, keyed by aggregator. A feed configured with its proxy
+/// is matched with the aggregator its `aggregator()` returns, so the answers keep being read when
+/// the proxy moves to a new aggregator. Emitters not matched yet are looked up again with a backoff.
+#[substreams::handlers::store]
+fn store_feed_aggregators(
+    params: String,
+    emitters_deltas: Deltas<DeltaInt64>, /* store_answer_emitters */
+    store: StoreSetString,
+) {
+    let params = OracleParams::parse(&params).unwrap();
+    let mut proxy_aggregators: Option<Vec<Option<Vec<u8>>>> = None;
+    for delta in emitters_deltas
+        .deltas
+        .iter()
+        .operation_not_eq(Operation::Delete)
+        .filter(|delta| rpc::should_refresh(delta.new_value))
+    {
+        let emitter = hex::decode(&delta.key).unwrap();
+        let feed = match params.feeds.iter().find(|feed| feed.address == emitter) {
+            Some(feed) => Some(feed),
+            None => {
+                let aggregators = proxy_aggregators.get_or_insert_with(|| rpc::feed_aggregators_call(&params.feeds));
+                params
+                    .feeds
+                    .iter()
+                    .zip(aggregators.iter())
+                    .find(|(_, aggregator)| aggregator.as_ref() == Some(&emitter))
+                    .map(|(feed, _)| feed)
+            }
+        };
+        if let Some(feed) = feed {
+            store.set(delta.ordinal, &delta.key, &Hex(&feed.address).to_string());
+        }
+    }
+}
+
+/// Last answer of every feed, keyed by token.
+#[substreams::handlers::store]
+fn store_last_answers(
+    params: String,
+    block: Block,
+    feed_aggregators_store: StoreGetString, /* store_feed_aggregators */
+    store: StoreSetProto<OracleAnswer>,
+) {
+    let params = OracleParams::parse(&params).unwrap();
+    for answer in feed_answers(&params, &block, &feed_aggregators_store) {
+        store.set(answer.ordinal, answer.token.clone(), &answer);
+    }
+}
+
+/// Chainlink answers of the configured feeds, compared with the DEX derived USD price of the token.
+/// The DEX price changes of the feed tokens are also compared with their last answer, at most once
+/// per token and block, and reported when they deviate.
+#[substreams::handlers::map]
+fn map_oracle_answers(
+    params: String,
+    block: Block,
+    eth_prices_store: StoreGetBigDecimal,            /* store_eth_prices */
+    eth_prices_deltas: Deltas<DeltaBigDecimal>,      /* store_eth_prices */
+    feed_aggregators_store: StoreGetString,          /* store_feed_aggregators */
+    last_answers_store: StoreGetProto<OracleAnswer>, /* store_last_answers */
+) -> Result<OracleAnswers, substreams::errors::Error> {
+    let params = OracleParams::parse(&params)?;
+    let mut answers = vec![];
+    if params.feeds.is_empty() {
+        return Ok(OracleAnswers { answers });
+    }
+    let deviation_threshold = BigDecimal::from(params.deviation_bps);
+    let dex_price_usd = |ord: u64, token: &str| {
+        eth_prices_store
+            .get_at(ord, format!("token:{token}:dprice:eth"))
+            .zip(eth_prices_store.get_at(ord, "bundle"))
+            .map(|(derived_eth_price, eth_price_usd)| derived_eth_price * eth_price_usd)
+    };
+    let compare = |answer: &mut OracleAnswer, dex_price_usd: BigDecimal| {
+        let price_usd = BigDecimal::try_from(answer.price_usd.clone()).unwrap();
+        let deviation_bps = oracle::deviation_bps(&price_usd, &dex_price_usd);
+        answer.dex_price_usd = dex_price_usd.to_string();
+        answer.deviation_exceeded = deviation_bps.gt(&deviation_threshold);
+        answer.deviation_bps = deviation_bps.to_string();
+    };
+
+    for mut answer in feed_answers(&params, &block, &feed_aggregators_store) {
+        if let Some(dex_price_usd) = dex_price_usd(answer.ordinal, &answer.token) {
+            compare(&mut answer, dex_price_usd);
+        }
+        answers.push(answer);
+    }
+
+    let mut dex_updates: BTreeMap<&str, u64> = BTreeMap::new();
+    for delta in eth_prices_deltas
+        .deltas
+        .iter()
+        .key_first_segment_eq("token")
+        .operation_not_eq(Operation::Delete)
+    {
+        let token = segment_at(&delta.key, 1);
+        if params.feeds.iter().any(|feed| feed.token == token) {
+            dex_updates.insert(token, delta.ordinal);
+        }
+    }
+    for (token, ord) in dex_updates {
+        let (mut answer, dex_price_usd) = match last_answers_store.get_at(ord, token).zip(dex_price_usd(ord, token)) {
+            Some(found) => found,
+            None => continue,
+        };
+        compare(&mut answer, dex_price_usd);
+        if !answer.deviation_exceeded {
+            continue;
+        }
+        answer.ordinal = ord;
+        answer.transaction_hash = String::new();
+        answer.dex_triggered = true;
+        answers.push(answer);
+    }
+    Ok(OracleAnswers { answers })
+}
+
+/// `AnswerUpdated` of the configured feeds in the block, without their DEX price.
+fn feed_answers(params: &OracleParams, block: &Block, feed_aggregators_store: &StoreGetString) -> Vec<OracleAnswer> {
+    let mut answers = vec![];
+    if params.feeds.is_empty() {
+        return answers;
+    }
+    for trx in block.transactions() {
+        for (log, _) in trx.logs_with_calls() {
+            let updated = match oracle::decode_answer_updated(&log.topics, &log.data) {
+                Some(updated) => updated,
+                None => continue,
+            };
+            let feed_address = match feed_aggregators_store.get_last(Hex(&log.address).to_string()) {
+                Some(feed_address) => feed_address,
+                None => continue,
+            };
+            let feed = match params
+                .feeds
+                .iter()
+                .find(|feed| Hex(&feed.address).to_string() == feed_address)
+            {
+                Some(feed) => feed,
+                None => continue,
+            };
+            answers.push(OracleAnswer {
+                feed: feed_address,
+                token: feed.token.clone(),
+                transaction_hash: Hex(&trx.hash).to_string(),
+                ordinal: log.ordinal,
+                round_id: updated.round_id.to_string(),
+                updated_at: updated.updated_at,
+                price_usd: updated.answer.to_decimal(feed.decimals).to_string(),
+                ..Default::default()
+            });
+        }
+    }
+    answers
+}
+
 #[substreams::handlers::store]
 fn store_token_info(pools: Pools, store: StoreSetIfNotExistsProto<Token>) {
     store_token_info_inner(pools, store)
 }
 
+/// Metadata of every token as read when its first pool was created, the lookups of later pools
+/// never overwrite it. Failed lookups are retried by `store_token_refreshes`.
 pub fn store_token_info_inner(pools: Pools, store: StoreSetIfNotExistsProto<Token>) {
     for pool in pools.pools {
-        let token0 = pool.token0.unwrap();
-        let token1 = pool.token1.unwrap();
-
-        store.set_if_not_exists(
-            pool.log_ordinal,
-            token0.address.clone(),
-            &Token {
-                address: token0.address,
-                name: token0.name,
-                symbol: token0.symbol,
-                decimals: token0.decimals,
-            },
-        );
-        store.set_if_not_exists(
-            pool.log_ordinal,
-            token1.address.clone(),
-            &Token {
-                address: token1.address,
-                name: token1.name,
-                symbol: token1.symbol,
-                decimals: token1.decimals,
-            },
-        );
+        for token in [pool.token0.unwrap(), pool.token1.unwrap()] {
+            store.set_if_not_exists(
+                pool.log_ordinal,
+                token.address.clone(),
+                &Token {
+                    address: token.address,
+                    name: token.name,
+                    symbol: token.symbol,
+                    decimals: token.decimals,
+                    total_supply: token.total_supply,
+                },
+            );
+        }
     }
 }
 
-pub fn pool_windows_id_fields(key: &String) -> (&str, &str, &str) {
-    let table_name = first_segment(key);
-    let time_id = segment_at(key, 1);
-    let pool_address = segment_at(key, 2);
+/// Blocks in which the price of a token with incomplete metadata changed.
+#[substreams::handlers::store]
+fn store_token_lookups(
+    price_deltas: Deltas<DeltaBigDecimal>,  /* store_eth_prices */
+    token_info_store: StoreGetProto<Token>, /* store_token_info */
+    store: StoreAddInt64,
+) {
+    let token_addresses: BTreeSet<&str> = price_deltas
+        .deltas
+        .iter()
+        .key_first_segment_eq("TokenHourData")
+        .operation_not_eq(Operation::Delete)
+        .map(|delta| segment_at(&delta.key, 2))
+        .collect();
+    for token_address in token_addresses {
+        let incomplete = token_info_store
+            .get_last(token_address)
+            .map_or(false, |token| tokens::is_incomplete(&token));
+        if incomplete {
+            store.add(0, token_address, 1);
+        }
+    }
+}
 
-    return (table_name, time_id, pool_address);
+/// Metadata of the tokens whose name, symbol or total supply lookup failed, read again with a
+/// backoff on the blocks their price changes in. Only complete lookups are kept, so a failed retry
+/// never replaces metadata an earlier one repaired.
+#[substreams::handlers::store]
+fn store_token_refreshes(
+    lookups_deltas: Deltas<DeltaInt64>,     /* store_token_lookups */
+    token_info_store: StoreGetProto<Token>, /* store_token_info */
+    store: StoreSetProto<Token>,
+) {
+    for delta in lookups_deltas
+        .deltas
+        .iter()
+        .operation_not_eq(Operation::Delete)
+        .filter(|delta| rpc::should_refresh(delta.new_value))
+    {
+        let token = match token_info_store.get_last(&delta.key) {
+            Some(token) => token,
+            None => continue,
+        };
+        let refreshed = rpc::refresh_token_call(&token);
+        if !tokens::is_incomplete(&refreshed) {
+            store.set(delta.ordinal, &delta.key, &refreshed);
+        }
+    }
+}
+
+#[substreams::handlers::store]
+fn store_token_origins(pools: Pools, store: StoreSetIfNotExistsProto<TokenOrigin>) {
+    for pool in pools.pools {
+        for token in [pool.token0.as_ref().unwrap(), pool.token1.as_ref().unwrap()] {
+            store.set_if_not_exists(
+                pool.log_ordinal,
+                token.address.clone(),
+                &TokenOrigin {
+                    block_number: pool.created_at_block_number,
+                    pool: pool.address.clone(),
+                },
+            );
+        }
+    }
 }
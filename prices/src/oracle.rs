@@ -0,0 +1,52 @@
+use substreams::hex;
+use substreams::scalar::{BigDecimal, BigInt};
+
+/// `AnswerUpdated(int256,uint256,uint256)`, emitted by Chainlink aggregators on every new round.
+const ANSWER_UPDATED_TOPIC: [u8; 32] = hex!("0559884fd3a460db3073b7fc896cc77986f16e378210ded43186175bf646fc5f");
+
+pub struct AnswerUpdated {
+    pub answer: BigInt,
+    pub round_id: BigInt,
+    pub updated_at: u64,
+}
+
+pub fn decode_answer_updated(topics: &[Vec<u8>], data: &[u8]) -> Option<AnswerUpdated> {
+    if topics.len() != 3 || topics[0] != ANSWER_UPDATED_TOPIC || data.len() < 32 {
+        return None;
+    }
+    Some(AnswerUpdated {
+        answer: BigInt::from_signed_bytes_be(&topics[1]),
+        round_id: BigInt::from_unsigned_bytes_be(&topics[2]),
+        updated_at: u64::from_be_bytes(data[24..32].try_into().unwrap()),
+    })
+}
+
+/// Distance of `price` from `reference` in basis points.
+pub fn deviation_bps(reference: &BigDecimal, price: &BigDecimal) -> BigDecimal {
+    if reference.eq(&BigDecimal::zero()) {
+        return BigDecimal::zero();
+    }
+    ((price.clone() - reference.clone()) / reference.clone()).absolute() * BigDecimal::from(10000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_answer_updated() {
+        let mut answer = vec![0u8; 24];
+        answer.extend_from_slice(&250_000_000_000u64.to_be_bytes());
+        let mut round_id = vec![0u8; 24];
+        round_id.extend_from_slice(&42u64.to_be_bytes());
+        let mut data = vec![0u8; 24];
+        data.extend_from_slice(&1_700_000_000u64.to_be_bytes());
+
+        let topics = vec![ANSWER_UPDATED_TOPIC.to_vec(), answer, round_id];
+        let updated = decode_answer_updated(&topics, &data).unwrap();
+        assert_eq!(BigInt::from(250_000_000_000u64), updated.answer);
+        assert_eq!(BigInt::from(42u64), updated.round_id);
+        assert_eq!(1_700_000_000, updated.updated_at);
+        assert!(decode_answer_updated(&topics[..2], &data).is_none());
+    }
+}
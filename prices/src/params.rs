@@ -0,0 +1,240 @@
+/// Module params of `db_out`, given as `key=value` pairs separated by `&`, e.g. `blockchain=ethereum`.
+#[derive(Debug, Default, PartialEq)]
+pub struct Params {
+    /// Chain name written to the `blockchain` column of every table.
+    pub blockchain: String,
+}
+
+impl Params {
+    pub fn parse(params: &str) -> Result<Params, substreams::errors::Error> {
+        let mut result = Params::default();
+        for pair in params.split('&').map(str::trim).filter(|p| !p.is_empty()) {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("invalid param `{}`, expected key=value", pair))?;
+            match key.trim() {
+                "blockchain" => result.blockchain = value.trim().to_string(),
+                _ => return Err(anyhow::anyhow!("unknown param `{}`", key)),
+            }
+        }
+        if result.blockchain.is_empty() {
+            return Err(anyhow::anyhow!("param `blockchain` is required"));
+        }
+        Ok(result)
+    }
+}
+
+/// Chainlink feed and the token its `AnswerUpdated` prices in USD.
+#[derive(Debug, PartialEq)]
+pub struct Feed {
+    /// Aggregator emitting the answers, or the proxy in front of it, see `store_feed_aggregators`.
+    pub address: Vec<u8>,
+    pub token: String,
+    pub decimals: u64,
+}
+
+/// Chainlink USD feed proxies of the anchor tokens of every chain, as `proxy:token`.
+const FEED_PRESETS: [(&str, &[&str]); 4] = [
+    (
+        "ethereum",
+        &[
+            // ETH / USD
+            "5f4ec3df9cbd43714fe2740f5e3616155c5b8419:c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2",
+            // BTC / USD
+            "f4030086522a5beea4988f8ca5b36dbc97bee88c:2260fac5e5542a773aa44fbcfedf7c193bc2c599",
+            // USDC / USD
+            "8fffffd4afb6115b954bd326cbe7b4ba576818f6:a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48",
+            // DAI / USD
+            "aed0c38402a5d19df6e4c03f4e2dced6e29c1ee9:6b175474e89094c44da98b954eedeac495271d0f",
+            // USDT / USD
+            "3e7d1eab13ad0104d2750b8863b489d65364e32d:dac17f958d2ee523a2206206994597c13d831ec7",
+            // LINK / USD
+            "2c1d072e956affc0d435cb7ac38ef18d24d9127c:514910771af9ca656af840dff83e8264ecf986ca",
+        ],
+    ),
+    (
+        "arbitrum-one",
+        &[
+            // ETH / USD
+            "639fe6ab55c921f74e7fac1ee960c0b6293ba612:82af49447d8a07e3bd95bd0d56f35241523fbab1",
+            // BTC / USD
+            "6ce185860a4963106506c203335a2910413708e9:2f2a2543b76a4166549f7aab2e75bef0aefc5b0f",
+            // USDC / USD
+            "50834f3163758fcc1df9973b6e91f0f0f0434ad3:ff970a61a04b1ca14834a43f5de4533ebddb5cc8",
+            // USDT / USD
+            "3f3f5df88dc9f13eac63df89ec16ef6e7e25dde7:fd086bc7cd5c481dcc9c85ebe478a1c0b69fcbb9",
+            // DAI / USD
+            "c5c8e77b397e531b8ec06bfb0048328b30e9ecfb:da10009cbd5d07dd0cecc66161fc93d7c9000da1",
+        ],
+    ),
+    (
+        "polygon",
+        &[
+            // ETH / USD
+            "f9680d99d6c9589e2a93a78a04a279e509205945:7ceb23fd6bc0add59e62ac25578270cff1b9f619",
+            // MATIC / USD
+            "ab594600376ec9fd91f8e885dadf0ce036862de0:0d500b1d8e8ef31e21c99d1db9a6444d3adf1270",
+            // BTC / USD
+            "c907e116054ad103354f2d350fd2514433d57f6f:1bfd67037b42cf73acf2047067bd4f2c47d9bfd6",
+            // USDC / USD
+            "fe4a8cc5b5b2366c1b58bea3858e81843581b2f7:2791bca1f2de4661ed88a30c99a7a9449aa84174",
+        ],
+    ),
+    (
+        "base",
+        &[
+            // ETH / USD
+            "71041dddad3595f9ced3dccfbe3d1f4b0a16bb70:4200000000000000000000000000000000000006",
+            // USDC / USD
+            "7e860098f58bbfc8648a4311b374b1d669a2bc6b:833589fcd6edb6e08f4c7c32d4f71b54bda02913",
+        ],
+    ),
+];
+
+/// Module params of `map_oracle_answers` and the oracle stores, e.g.
+/// `chain=ethereum&feeds=<aggregator or proxy>:<token>[:<decimals>],...&deviation_bps=100`.
+/// The feeds of `chain` come first, followed by the ones of `feeds`.
+#[derive(Debug, PartialEq)]
+pub struct OracleParams {
+    pub feeds: Vec<Feed>,
+    /// DEX prices further than this from the reference price are reported as deviations.
+    pub deviation_bps: u64,
+}
+
+impl OracleParams {
+    pub fn parse(params: &str) -> Result<OracleParams, substreams::errors::Error> {
+        let mut result = OracleParams {
+            feeds: vec![],
+            deviation_bps: 100,
+        };
+        for pair in params.split('&').map(str::trim).filter(|p| !p.is_empty()) {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("invalid param `{}`, expected key=value", pair))?;
+            match key.trim() {
+                "chain" => {
+                    let (_, presets) = FEED_PRESETS
+                        .iter()
+                        .find(|(chain, _)| *chain == value.trim())
+                        .ok_or_else(|| anyhow::anyhow!("unknown chain `{}`", value))?;
+                    let mut feeds = presets.iter().copied().map(parse_feed).collect::<Result<Vec<_>, _>>()?;
+                    feeds.append(&mut result.feeds);
+                    result.feeds = feeds;
+                }
+                "feeds" => {
+                    let feeds = value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|feed| !feed.is_empty())
+                        .map(parse_feed)
+                        .collect::<Result<Vec<_>, _>>()?;
+                    result.feeds.extend(feeds);
+                }
+                "deviation_bps" => {
+                    result.deviation_bps = value
+                        .trim()
+                        .parse()
+                        .map_err(|_| anyhow::anyhow!("invalid deviation_bps `{}`", value))?
+                }
+                _ => return Err(anyhow::anyhow!("unknown param `{}`", key)),
+            }
+        }
+        Ok(result)
+    }
+}
+
+fn parse_feed(feed: &str) -> Result<Feed, substreams::errors::Error> {
+    let parts: Vec<&str> = feed.split(':').collect();
+    if parts.len() < 2 || parts.len() > 3 {
+        return Err(anyhow::anyhow!(
+            "invalid feed `{}`, expected aggregator:token[:decimals]",
+            feed
+        ));
+    }
+    let address = |value: &str| {
+        let value = value.trim().trim_start_matches("0x").to_lowercase();
+        match hex::decode(&value) {
+            Ok(bytes) if bytes.len() == 20 => Ok(value),
+            _ => Err(anyhow::anyhow!("invalid address `{}` in feed `{}`", value, feed)),
+        }
+    };
+    let decimals = match parts.get(2) {
+        Some(decimals) => decimals
+            .trim()
+            .parse()
+            .map_err(|_| anyhow::anyhow!("invalid decimals in feed `{}`", feed))?,
+        // USD feeds answer with 8 decimals
+        None => 8,
+    };
+    Ok(Feed {
+        address: hex::decode(address(parts[0])?).unwrap(),
+        token: address(parts[1])?,
+        decimals,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_blockchain() {
+        assert_eq!("polygon", Params::parse("blockchain=polygon").unwrap().blockchain);
+        assert_eq!("base", Params::parse(" blockchain = base ").unwrap().blockchain);
+    }
+
+    #[test]
+    fn test_parse_invalid_params() {
+        assert!(Params::parse("").is_err());
+        assert!(Params::parse("blockchain=").is_err());
+        assert!(Params::parse("blockchain").is_err());
+        assert!(Params::parse("unknown=true").is_err());
+    }
+
+    #[test]
+    fn test_parse_oracle_params() {
+        let params = OracleParams::parse(
+            "feeds=0x0000000000000000000000000000000000000001:C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2,\
+             0000000000000000000000000000000000000002:2260fac5e5542a773aa44fbcfedf7c193bc2c599:18&deviation_bps=250",
+        )
+        .unwrap();
+        assert_eq!(250, params.deviation_bps);
+        assert_eq!(2, params.feeds.len());
+        assert_eq!(
+            Feed {
+                address: hex::decode("0000000000000000000000000000000000000001").unwrap(),
+                token: "c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2".to_string(),
+                decimals: 8,
+            },
+            params.feeds[0]
+        );
+        assert_eq!(18, params.feeds[1].decimals);
+
+        assert_eq!(100, OracleParams::parse("").unwrap().deviation_bps);
+        assert!(OracleParams::parse("feeds=0x01:0x02").is_err());
+        assert!(OracleParams::parse("deviation_bps=high").is_err());
+    }
+
+    #[test]
+    fn test_parse_oracle_chain_presets() {
+        for (chain, presets) in FEED_PRESETS {
+            let params = OracleParams::parse(&format!("chain={chain}")).unwrap();
+            assert_eq!(presets.len(), params.feeds.len());
+        }
+
+        let params = OracleParams::parse(
+            "feeds=0000000000000000000000000000000000000001:c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2&chain=base",
+        )
+        .unwrap();
+        assert_eq!(3, params.feeds.len());
+        assert_eq!(
+            hex::decode("71041dddad3595f9ced3dccfbe3d1f4b0a16bb70").unwrap(),
+            params.feeds[0].address
+        );
+        assert_eq!(
+            hex::decode("0000000000000000000000000000000000000001").unwrap(),
+            params.feeds[2].address
+        );
+        assert!(OracleParams::parse("chain=unknown").is_err());
+    }
+}
@@ -0,0 +1,49 @@
+use std::collections::BTreeMap;
+
+use substreams::key::{first_segment, last_segment, segment_at};
+use substreams::pb::substreams::store_delta::Operation;
+use substreams::scalar::BigDecimal;
+use substreams::store::{DeltaBigDecimal, DeltaExt, Deltas};
+
+/// Hourly peg of a stablecoin, its market price and its ratio to the other stablecoins.
+pub struct Peg {
+    pub price_usd: BigDecimal,
+    pub peg_ratio: BigDecimal,
+    pub depegged: bool,
+}
+
+impl Default for Peg {
+    fn default() -> Self {
+        Peg {
+            price_usd: BigDecimal::zero(),
+            peg_ratio: BigDecimal::one(),
+            depegged: false,
+        }
+    }
+}
+
+/// Pegs of the hours closed in this block, keyed by hour id and stablecoin address.
+pub fn closed_pegs(stablecoin_pegs_deltas: &Deltas<DeltaBigDecimal>) -> BTreeMap<(u64, String), Peg> {
+    let mut pegs: BTreeMap<(u64, String), Peg> = BTreeMap::new();
+
+    for delta in stablecoin_pegs_deltas.deltas.iter().operation_eq(Operation::Delete) {
+        if first_segment(&delta.key) != "StablecoinHourData" {
+            continue;
+        }
+        let hour_id = match segment_at(&delta.key, 1).parse::<u64>() {
+            Ok(hour_id) => hour_id,
+            Err(_) => continue,
+        };
+        let peg = pegs
+            .entry((hour_id, segment_at(&delta.key, 2).to_string()))
+            .or_default();
+        match last_segment(&delta.key) {
+            "price" => peg.price_usd = delta.old_value.clone(),
+            "peg" => peg.peg_ratio = delta.old_value.clone(),
+            "depegged" => peg.depegged = delta.old_value.eq(&BigDecimal::one()),
+            _ => {}
+        }
+    }
+
+    pegs
+}
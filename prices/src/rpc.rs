@@ -0,0 +1,130 @@
+use substreams::{log, scalar::BigInt, Hex};
+use substreams_ethereum::{
+    pb::eth::rpc::{RpcCall, RpcCalls},
+    rpc::eth_call,
+};
+
+use crate::params::Feed;
+use crate::pb::semiotic::price::Token;
+
+// name()
+const NAME_SELECTOR: [u8; 4] = [0x06, 0xfd, 0xde, 0x03];
+// symbol()
+const SYMBOL_SELECTOR: [u8; 4] = [0x95, 0xd8, 0x9b, 0x41];
+// totalSupply()
+const TOTAL_SUPPLY_SELECTOR: [u8; 4] = [0x18, 0x16, 0x0d, 0xdd];
+// aggregator()
+const AGGREGATOR_SELECTOR: [u8; 4] = [0x24, 0x5a, 0x7b, 0xfc];
+
+/// Lookups are given up after this many blocks, the 17th attempt.
+const MAX_BLOCKS_SEEN: i64 = 1 << 16;
+
+/// Tokens with a failed lookup are fetched again once their price changed in 1, 2, 4, 8... blocks,
+/// so a failed call is retried without a call for every block of the token. Answer emitters not
+/// matched with a feed yet are looked up on the same schedule.
+pub fn should_refresh(blocks_seen: i64) -> bool {
+    blocks_seen > 0 && blocks_seen <= MAX_BLOCKS_SEEN && blocks_seen & (blocks_seen - 1) == 0
+}
+
+/// Name, symbol and total supply of the token read again, the fields whose call fails again are
+/// kept as they were.
+pub fn refresh_token_call(token: &Token) -> Token {
+    let address = match hex::decode(&token.address) {
+        Ok(address) => address,
+        Err(_) => return token.clone(),
+    };
+    let responses = eth_call(&RpcCalls {
+        calls: [NAME_SELECTOR, SYMBOL_SELECTOR, TOTAL_SUPPLY_SELECTOR]
+            .iter()
+            .map(|selector| RpcCall {
+                to_addr: address.clone(),
+                data: selector.to_vec(),
+            })
+            .collect(),
+    })
+    .responses;
+    let raw = |index: usize| {
+        responses
+            .get(index)
+            .filter(|response| !response.failed)
+            .map(|response| response.raw.as_slice())
+    };
+
+    let mut refreshed = token.clone();
+    if let Some(name) = raw(0).and_then(decode_string) {
+        refreshed.name = name;
+    }
+    if let Some(symbol) = raw(1).and_then(decode_string) {
+        refreshed.symbol = symbol;
+    }
+    match raw(2).filter(|raw| raw.len() >= 32) {
+        Some(raw) => refreshed.total_supply = BigInt::from_unsigned_bytes_be(&raw[0..32]).to_string(),
+        None => log::debug!("{} totalSupply `eth_call` failed", Hex(&address)),
+    }
+    refreshed
+}
+
+/// Aggregator behind every feed proxy, `None` for the feeds that are aggregators themselves.
+pub fn feed_aggregators_call(feeds: &[Feed]) -> Vec<Option<Vec<u8>>> {
+    if feeds.is_empty() {
+        return vec![];
+    }
+    let responses = eth_call(&RpcCalls {
+        calls: feeds
+            .iter()
+            .map(|feed| RpcCall {
+                to_addr: feed.address.clone(),
+                data: AGGREGATOR_SELECTOR.to_vec(),
+            })
+            .collect(),
+    })
+    .responses;
+    (0..feeds.len())
+        .map(|index| {
+            responses
+                .get(index)
+                .filter(|response| !response.failed && response.raw.len() >= 32)
+                .map(|response| response.raw[12..32].to_vec())
+        })
+        .collect()
+}
+
+/// ABI encoded `string`, or the `bytes32` some older tokens return instead.
+fn decode_string(raw: &[u8]) -> Option<String> {
+    if raw.len() == 32 {
+        let end = raw.iter().position(|byte| *byte == 0).unwrap_or(raw.len());
+        return String::from_utf8(raw[..end].to_vec())
+            .ok()
+            .filter(|value| !value.is_empty());
+    }
+    match ethabi::decode(&[ethabi::ParamType::String], raw).ok()?.pop()? {
+        ethabi::Token::String(value) if !value.is_empty() => Some(value),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_refresh() {
+        let refreshed: Vec<i64> = (0..20).filter(|count| should_refresh(*count)).collect();
+        assert_eq!(refreshed, vec![1, 2, 4, 8, 16]);
+        assert!(should_refresh(1 << 16));
+        assert!(!should_refresh(1 << 17));
+    }
+
+    #[test]
+    fn test_decode_string() {
+        let encoded = ethabi::encode(&[ethabi::Token::String("Wrapped Ether".to_string())]);
+        assert_eq!(Some("Wrapped Ether".to_string()), decode_string(&encoded));
+
+        let mut bytes32 = b"MKR".to_vec();
+        bytes32.resize(32, 0);
+        assert_eq!(Some("MKR".to_string()), decode_string(&bytes32));
+
+        assert_eq!(None, decode_string(&[0u8; 32]));
+        assert_eq!(None, decode_string(&[]));
+    }
+}
@@ -0,0 +1,88 @@
+use std::collections::BTreeMap;
+
+use substreams::pb::substreams::store_delta::Operation;
+use substreams::store::{DeltaProto, Deltas};
+
+use crate::pb::semiotic::price::Token;
+
+/// Token whose name, symbol or total supply lookup failed when its first pool was created. A zero
+/// total supply is a value read from the token, not a failed lookup.
+pub fn is_incomplete(token: &Token) -> bool {
+    token.name.is_empty() || token.symbol.is_empty() || token.total_supply.is_empty()
+}
+
+/// Tokens whose metadata was set or changed in this block, keyed by address. A token changed
+/// several times in the block is written once with its last metadata, the refreshes of
+/// `store_token_refreshes` coming after the ones of `store_token_info`.
+pub fn changed_tokens(
+    token_info_deltas: &Deltas<DeltaProto<Token>>,
+    token_refreshes_deltas: &Deltas<DeltaProto<Token>>,
+) -> BTreeMap<String, Token> {
+    let mut tokens = BTreeMap::new();
+    for delta in token_info_deltas
+        .deltas
+        .iter()
+        .chain(token_refreshes_deltas.deltas.iter())
+    {
+        let changed = delta.operation == Operation::Create
+            || (delta.operation == Operation::Update && delta.old_value != delta.new_value);
+        if changed {
+            tokens.insert(delta.new_value.address.clone(), delta.new_value.clone());
+        }
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(symbol: &str, total_supply: &str) -> Token {
+        Token {
+            address: "c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2".to_string(),
+            symbol: symbol.to_string(),
+            name: "Wrapped Ether".to_string(),
+            decimals: 18,
+            total_supply: total_supply.to_string(),
+        }
+    }
+
+    fn delta(operation: Operation, old_value: Token, new_value: Token) -> DeltaProto<Token> {
+        DeltaProto {
+            operation,
+            ordinal: 1,
+            key: new_value.address.clone(),
+            old_value,
+            new_value,
+        }
+    }
+
+    #[test]
+    fn test_is_incomplete() {
+        assert!(!is_incomplete(&token("WETH", "1000")));
+        assert!(is_incomplete(&token("", "1000")));
+        assert!(is_incomplete(&token("WETH", "")));
+        assert!(!is_incomplete(&token("WETH", "0")));
+    }
+
+    #[test]
+    fn test_changed_tokens_once_per_block() {
+        let info = Deltas {
+            deltas: vec![
+                delta(Operation::Create, Token::default(), token("", "0")),
+                delta(Operation::Update, token("", "0"), token("", "1000")),
+            ],
+        };
+        let refreshes = Deltas {
+            deltas: vec![delta(Operation::Create, Token::default(), token("WETH", "1000"))],
+        };
+        let tokens = changed_tokens(&info, &refreshes);
+        assert_eq!(1, tokens.len());
+        assert_eq!(&token("WETH", "1000"), tokens.values().next().unwrap());
+
+        let unchanged = Deltas {
+            deltas: vec![delta(Operation::Update, token("WETH", "1000"), token("WETH", "1000"))],
+        };
+        assert!(changed_tokens(&unchanged, &Deltas { deltas: vec![] }).is_empty());
+    }
+}
@@ -9,7 +9,7 @@ use std::unimplemented;
 
 use syn::{parse_macro_input, Data, DeriveInput, Fields};
 
-#[proc_macro_derive(ToTableChange,attributes(table_prefix))]
+#[proc_macro_derive(ToTableChange,attributes(table_prefix,token_amounts))]
 pub fn to_table(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     // Construct a string representation of the type definition
 
@@ -27,23 +27,17 @@ fn impl_to_table(ast: &syn::DeriveInput) -> TokenStream {
 
     let name = &ast.ident;
 
-    let table_name = ast.attrs.iter().filter(|attr| attr.path().is_ident("table_prefix")).map(|attr| {
-        if let syn::Meta::NameValue(name_value) = &attr.meta {
-            if let syn::Expr::Lit(lit)  = &name_value.value {
-                if let syn::Lit::Str(lit_str) = &lit.lit {
-                    return Some(lit_str.value());
-                }
-            }
-        }
-        None
-    }).next().flatten();
+    let table_name = str_attribute(ast, "table_prefix");
     
     let mut table_name = table_name.unwrap_or(String::new());
     table_name.push_str(name.to_string().to_lowercase().as_str());
 
+    let token_amounts = str_attribute(ast, "token_amounts").unwrap_or(String::new());
+
     let changes = add_table_changes_method(name, &ast.data);
     let table_name = add_table_name_method(name, &ast.data,table_name);
     let contract_name = add_contract_name_method(name, &ast.data);
+    let token_amounts = add_token_amounts_method(name, &ast.data, token_amounts);
 
     quote! {
         impl crate::ToTableChange for #name {
@@ -53,11 +47,26 @@ fn impl_to_table(ast: &syn::DeriveInput) -> TokenStream {
             #table_name
 
             #contract_name
+
+            #token_amounts
             
         }
     }
 }
 
+fn str_attribute(ast: &syn::DeriveInput, attribute: &str) -> Option<String> {
+    ast.attrs.iter().filter(|attr| attr.path().is_ident(attribute)).map(|attr| {
+        if let syn::Meta::NameValue(name_value) = &attr.meta {
+            if let syn::Expr::Lit(lit)  = &name_value.value {
+                if let syn::Lit::Str(lit_str) = &lit.lit {
+                    return Some(lit_str.value());
+                }
+            }
+        }
+        None
+    }).next().flatten()
+}
+
 fn add_table_name_method(name: &Ident, data: &Data,table_name:String) -> TokenStream {
     match *data {
         Data::Union(_) | Data::Struct(_) => {
@@ -123,6 +132,81 @@ fn add_contract_name_method(name: &Ident, data: &Data) -> TokenStream {
     }
 }
 
+// Amount and token fields of one event in the `token_amounts` attribute. The attribute is
+// shared by every event of a contract and has the form `Event:amount_field:token,...` where
+// token is either `contract` (the emitting contract) or the name of the address field holding
+// the token.
+fn token_amount_specs<'a>(token_amounts: &'a str, event_name: &str) -> Vec<(&'a str, &'a str)> {
+    token_amounts
+        .split(',')
+        .filter_map(|spec| {
+            let parts: Vec<_> = spec.split(':').collect();
+            match parts.as_slice() {
+                [event, amount, token] if *event == event_name => Some((*amount, *token)),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+fn add_token_amounts_method(name: &Ident, data: &Data, token_amounts: String) -> TokenStream {
+    match *data {
+        Data::Union(_) => unimplemented!(),
+        Data::Struct(_) => {
+            let amounts = token_amount_specs(&token_amounts, &name.to_string())
+                .into_iter()
+                .map(|(amount, token)| {
+                    let amount_field = format_ident!("{}", amount);
+                    let token = match token {
+                        "contract" => quote! { crate::events::TokenSource::Contract },
+                        field => {
+                            let token_field = format_ident!("{}", field);
+                            quote! { crate::events::TokenSource::Address(self.#token_field.clone()) }
+                        }
+                    };
+                    quote! {
+                        crate::events::TokenAmount {
+                            column: #amount,
+                            token: #token,
+                            amount: self.#amount_field.clone(),
+                        }
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            if amounts.is_empty() {
+                return quote! {};
+            }
+
+            quote! {
+                fn get_token_amounts(&self) -> Vec<crate::events::TokenAmount> {
+                    vec![#(#amounts,)*]
+                }
+            }
+        }
+        Data::Enum(ref data) => {
+            let recurse = data.variants.iter().map(|f| {
+                match (f.fields.len(), f.fields.iter().collect::<Vec<_>>().first()) {
+                    (1, Some(_)) => {
+                        let ident = &f.ident;
+                        quote_spanned! {f.span()=>
+                            #name::#ident(e) => e.get_token_amounts()
+                        }
+                    }
+                    _ => unimplemented!(),
+                }
+            });
+            quote! {
+                fn get_token_amounts(&self) -> Vec<crate::events::TokenAmount> {
+                    match self {
+                        #(#recurse,)*
+                    }
+                }
+            }
+        }
+    }
+}
+
 // Generate an expression to sum up the heap size of each field.
 fn add_table_changes_method(name: &Ident, data: &Data) -> TokenStream {
     match *data {
@@ -169,3 +253,21 @@ fn add_table_changes_method(name: &Ident, data: &Data) -> TokenStream {
         Data::Union(_) => unimplemented!(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::token_amount_specs;
+
+    #[test]
+    fn test_token_amount_specs() {
+        let token_amounts = "Transfer:value:contract,Swap:amount_in:token_in,Swap:amount_out:token_out";
+
+        assert_eq!(token_amount_specs(token_amounts, "Transfer"), vec![("value", "contract")]);
+        assert_eq!(
+            token_amount_specs(token_amounts, "Swap"),
+            vec![("amount_in", "token_in"), ("amount_out", "token_out")]
+        );
+        assert!(token_amount_specs(token_amounts, "Approval").is_empty());
+        assert!(token_amount_specs("", "Transfer").is_empty());
+    }
+}
@@ -0,0 +1,39 @@
+use anyhow::{format_err, Result};
+use std::{collections::BTreeMap, env, fs, io::Write, path::PathBuf};
+
+const SELECTORS_FILE: &str = "selectors.tsv";
+
+fn main() -> Result<(), anyhow::Error> {
+    println!("cargo:rerun-if-changed={}", SELECTORS_FILE);
+
+    let mut selectors = BTreeMap::new();
+    for (line_number, line) in fs::read_to_string(SELECTORS_FILE)?.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (selector, signature) = line.split_once('\t').ok_or_else(|| {
+            format_err!(
+                "{}:{} expected `selector<TAB>signature`",
+                SELECTORS_FILE,
+                line_number + 1
+            )
+        })?;
+        let selector = u32::from_str_radix(selector.trim_start_matches("0x"), 16)
+            .map_err(|_| format_err!("{}:{} invalid selector `{}`", SELECTORS_FILE, line_number + 1, selector))?;
+        // first signature wins for colliding selectors
+        selectors
+            .entry(selector)
+            .or_insert_with(|| signature.trim().to_string());
+    }
+
+    let out_path = PathBuf::from(env::var("OUT_DIR")?).join("selectors.rs");
+    let mut writer = fs::File::create(out_path)?;
+    writeln!(writer, "pub static SELECTORS: [(u32, &str); {}] = [", selectors.len())?;
+    for (selector, signature) in selectors {
+        writeln!(writer, "    (0x{:08x}, {:?}),", selector, signature)?;
+    }
+    writeln!(writer, "];")?;
+
+    Ok(())
+}
@@ -0,0 +1,206 @@
+CREATE DATABASE IF NOT EXISTS {{DATABASE}};
+
+CREATE TABLE IF NOT EXISTS {{DATABASE}}.transactions (
+    hash FixedString(64),
+    block_number UInt64,
+    block_time DateTime,
+    block_hash FixedString(64),
+    status FixedString(9),
+    value UInt256,
+    success Bool,
+    gas_used UInt64,
+    gas_price UInt256,
+    gas_limit UInt64,
+    max_fee_per_gas UInt256,
+    max_priority_fee_per_gas UInt256,
+    nonce UInt64,
+    "index" UInt64,
+    "from" FixedString(40),
+    "to" FixedString(40),
+    input String,
+    return_data String,
+    method_id FixedString(8),
+    method_signature String,
+    "type" String,
+    failure_reason String,
+    revert_reason String,
+    revert_selector String,
+    max_fee_per_blob_gas UInt256,
+    blob_versioned_hashes Array(FixedString(64)),
+    cumulative_gas_used UInt64,
+    logs_bloom FixedString(512),
+    blob_gas_used UInt64,
+    blob_gas_price UInt256,
+    created_contract_address String,
+    effective_gas_price UInt256,
+    effective_priority_fee_per_gas UInt256,
+    burnt_fee UInt256,
+    miner_tip UInt256,
+    total_fee UInt256,
+
+    PRIMARY KEY (hash, block_number, block_time, block_hash)
+) Engine = MergeTree();
+
+CREATE TABLE IF NOT EXISTS {{DATABASE}}.logs (
+    tx_hash FixedString(64),
+    log_index UInt32,
+    block_number UInt64,
+    block_time DateTime,
+    block_hash FixedString(64),
+    tx_index UInt64,
+    tx_log_index UInt32,
+    address FixedString(40),
+    topics Array(FixedString(64)),
+    "data" String,
+
+    PRIMARY KEY (block_time, block_number, tx_hash, log_index)
+) Engine = MergeTree();
+
+CREATE TABLE IF NOT EXISTS {{DATABASE}}.access_list (
+    tx_hash FixedString(64),
+    access_index UInt32,
+    block_number UInt64,
+    block_time DateTime,
+    address FixedString(40),
+    storage_keys Array(FixedString(64)),
+
+    PRIMARY KEY (block_time, block_number, tx_hash, access_index)
+) Engine = MergeTree();
+
+CREATE TABLE IF NOT EXISTS {{DATABASE}}.traces (
+    tx_hash FixedString(64),
+    call_index UInt32,
+    block_number UInt64,
+    block_time DateTime,
+    block_hash FixedString(64),
+    tx_index UInt64,
+    parent_index UInt32,
+    depth UInt32,
+    call_type LowCardinality(String),
+    caller FixedString(40),
+    address FixedString(40),
+    value UInt256,
+    gas_limit UInt64,
+    gas_consumed UInt64,
+    input String,
+    output String,
+    status_failed Bool,
+    status_reverted Bool,
+    state_reverted Bool,
+    failure_reason String,
+
+    PRIMARY KEY (block_time, block_number, tx_hash, call_index)
+) Engine = MergeTree();
+
+CREATE TABLE IF NOT EXISTS {{DATABASE}}.storage_changes (
+    tx_hash FixedString(64),
+    address FixedString(40),
+    slot FixedString(64),
+    ordinal UInt64,
+    block_number UInt64,
+    block_time DateTime,
+    block_hash FixedString(64),
+    tx_index UInt64,
+    call_index UInt32,
+    old_value FixedString(64),
+    new_value FixedString(64),
+
+    PRIMARY KEY (address, slot, block_number, ordinal)
+) Engine = MergeTree();
+
+CREATE TABLE IF NOT EXISTS {{DATABASE}}.blocks (
+    blockchain String,
+    time DateTime,
+    "number" UInt64,
+    hash FixedString(64),
+    parent_hash FixedString(64),
+    gas_limit UInt64,
+    gas_used UInt64,
+    miner FixedString(40),
+    difficulty UInt256,
+    total_difficulty UInt256,
+    nonce UInt64,
+    base_fee_per_gas UInt256,
+    tx_count UInt64,
+    unique_senders UInt64,
+    total_burnt_fee UInt256,
+    total_tips UInt256,
+    gas_price_p10 UInt256,
+    gas_price_p25 UInt256,
+    gas_price_p50 UInt256,
+    gas_price_p75 UInt256,
+    gas_price_p90 UInt256,
+    withdrawals_root String,
+    blob_gas_used UInt64,
+    excess_blob_gas UInt64,
+    withdrawal_count UInt64,
+    withdrawal_amount UInt256,
+    l1_block_number UInt64,
+    PRIMARY KEY (time, number, hash)
+) Engine = MergeTree();
+
+CREATE TABLE IF NOT EXISTS {{DATABASE}}.withdrawals (
+    block_time DateTime,
+    block_number UInt64,
+    ordinal UInt64,
+    block_hash FixedString(64),
+    address FixedString(40),
+    amount UInt256,
+
+    PRIMARY KEY (block_time, block_number, ordinal)
+) Engine = MergeTree();
+
+CREATE TABLE IF NOT EXISTS {{DATABASE}}.balance_changes (
+    block_time DateTime,
+    block_number UInt64,
+    ordinal UInt64,
+    block_hash FixedString(64),
+    tx_hash String,
+    address FixedString(40),
+    old_value UInt256,
+    new_value UInt256,
+    reason String,
+
+    PRIMARY KEY (block_time, block_number, ordinal)
+) Engine = MergeTree();
+
+CREATE TABLE IF NOT EXISTS {{DATABASE}}.native_transfers (
+    tx_hash FixedString(64),
+    call_index UInt32,
+    block_number UInt64,
+    block_time DateTime,
+    block_hash FixedString(64),
+    tx_index UInt64,
+    depth UInt32,
+    call_type LowCardinality(String),
+    "from" FixedString(40),
+    "to" FixedString(40),
+    value UInt256,
+
+    PRIMARY KEY (block_time, block_number, tx_hash, call_index)
+) Engine = MergeTree();
+
+CREATE TABLE IF NOT EXISTS {{DATABASE}}.contracts (
+    address FixedString(40),
+    block_number UInt64,
+    ordinal UInt64,
+    block_time DateTime,
+    block_hash FixedString(64),
+    tx_hash FixedString(64),
+    tx_index UInt64,
+    tx_from FixedString(40),
+    deployer FixedString(40),
+    kind String,
+    code_hash String,
+    bytecode_size UInt64,
+
+    PRIMARY KEY (address, block_number, ordinal)
+) Engine = MergeTree();
+
+CREATE TABLE IF NOT EXISTS {{DATABASE}}.cursors
+(
+    id         String,
+	cursor     String,
+	block_num  Int64,
+	block_id   String
+) Engine = ReplacingMergeTree() ORDER BY id;
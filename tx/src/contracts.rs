@@ -0,0 +1,150 @@
+use substreams_ethereum::pb::eth::v2::{Call, TransactionTrace};
+use tiny_keccak::{Hasher, Keccak};
+
+/// Opcode that deployed a contract.
+///
+/// Firehose only has a `CREATE` call type, `CREATE2` deployments are told apart by recomputing the
+/// `CREATE` address from the deployer nonce.
+pub fn creation_kind(tx: &TransactionTrace, call: &Call) -> &'static str {
+    let nonce = if call.depth == 0 {
+        Some(tx.nonce)
+    } else {
+        deployer_nonce(tx, call)
+    };
+    match nonce {
+        Some(nonce) if create_address(&call.caller, nonce) == call.address => "CREATE",
+        Some(_) => "CREATE2",
+        None => "UNKNOWN",
+    }
+}
+
+/// Nonce of the deployer before the create call bumped it. The bump is recorded either in the
+/// create call itself or in its parent right before the create call begins.
+fn deployer_nonce(tx: &TransactionTrace, call: &Call) -> Option<u64> {
+    call.nonce_changes
+        .iter()
+        .filter(|nc| nc.address == call.caller)
+        .min_by_key(|nc| nc.ordinal)
+        .or_else(|| {
+            tx.calls
+                .iter()
+                .flat_map(|c| c.nonce_changes.iter())
+                .filter(|nc| nc.address == call.caller && nc.ordinal <= call.begin_ordinal)
+                .max_by_key(|nc| nc.ordinal)
+        })
+        .map(|nc| nc.old_value)
+}
+
+/// Address of a contract deployed with `CREATE`, `keccak256(rlp([sender, nonce]))[12..]`.
+pub fn create_address(sender: &[u8], nonce: u64) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(30);
+    payload.push(0x80 + sender.len() as u8);
+    payload.extend_from_slice(sender);
+    match nonce {
+        0 => payload.push(0x80),
+        1..=0x7f => payload.push(nonce as u8),
+        _ => {
+            let bytes = nonce.to_be_bytes();
+            let bytes = &bytes[nonce.leading_zeros() as usize / 8..];
+            payload.push(0x80 + bytes.len() as u8);
+            payload.extend_from_slice(bytes);
+        }
+    }
+
+    let mut hasher = Keccak::v256();
+    hasher.update(&[0xc0 + payload.len() as u8]);
+    hasher.update(&payload);
+    let mut hash = [0u8; 32];
+    hasher.finalize(&mut hash);
+    hash[12..].to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use substreams_ethereum::pb::eth::v2::NonceChange;
+
+    fn nonce_change(address: &[u8], old_value: u64, ordinal: u64) -> NonceChange {
+        NonceChange {
+            address: address.to_vec(),
+            old_value,
+            new_value: old_value + 1,
+            ordinal,
+        }
+    }
+
+    #[test]
+    fn test_creation_kind_nonce_bump_inside_call() {
+        let factory = hex::decode("6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0").unwrap();
+        // an earlier deployment of the factory bumped its nonce from 1 to 2
+        let parent = Call {
+            depth: 0,
+            address: factory.clone(),
+            begin_ordinal: 1,
+            nonce_changes: vec![nonce_change(&factory, 1, 5)],
+            ..Default::default()
+        };
+        // this deployment bumps it from 2 to 3 inside the create call
+        let create = Call {
+            depth: 1,
+            caller: factory.clone(),
+            address: create_address(&factory, 2),
+            begin_ordinal: 10,
+            nonce_changes: vec![nonce_change(&factory, 2, 11)],
+            ..Default::default()
+        };
+        let create2 = Call {
+            address: hex::decode("00000000000000000000000000000000000000aa").unwrap(),
+            ..create.clone()
+        };
+        let tx = TransactionTrace {
+            calls: vec![parent, create.clone()],
+            ..Default::default()
+        };
+
+        assert_eq!("CREATE", creation_kind(&tx, &create));
+        assert_eq!("CREATE2", creation_kind(&tx, &create2));
+    }
+
+    #[test]
+    fn test_creation_kind_nonce_bump_before_call() {
+        let factory = hex::decode("6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0").unwrap();
+        let parent = Call {
+            depth: 0,
+            address: factory.clone(),
+            begin_ordinal: 1,
+            nonce_changes: vec![nonce_change(&factory, 1, 5), nonce_change(&factory, 2, 9)],
+            ..Default::default()
+        };
+        let create = Call {
+            depth: 1,
+            caller: factory.clone(),
+            address: create_address(&factory, 2),
+            begin_ordinal: 10,
+            ..Default::default()
+        };
+        let tx = TransactionTrace {
+            calls: vec![parent, create.clone()],
+            ..Default::default()
+        };
+
+        assert_eq!("CREATE", creation_kind(&tx, &create));
+    }
+
+    #[test]
+    fn test_create_address() {
+        let sender = hex::decode("6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0").unwrap();
+        let expected = [
+            (0, "cd234a471b72ba2f1ccf0a70fcaba648a5eecd8d"),
+            (1, "343c43a37d37dff08ae8c4a11544c718abb4fcf8"),
+            (2, "f778b86fa74e846c4f0a1fbd1335fe81c00a0c91"),
+            (3, "fffd933a0bc612844eaf0c6fe3e5b8e9b6c1d19c"),
+        ];
+        for (nonce, address) in expected {
+            assert_eq!(
+                hex::decode(address).unwrap(),
+                create_address(&sender, nonce)
+            );
+        }
+    }
+}
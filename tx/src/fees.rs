@@ -0,0 +1,128 @@
+use substreams::scalar::BigInt;
+use substreams_ethereum::pb::eth::v2::TransactionTrace;
+
+use crate::to_big_int;
+
+/// Fees paid by a transaction, all amounts are in wei.
+pub struct Fees {
+    /// Price per gas actually paid, EIP-1559 transactions pay at most `base_fee + max_priority_fee`.
+    pub effective_gas_price: BigInt,
+    /// Part of the effective gas price going to the block producer.
+    pub effective_priority_fee_per_gas: BigInt,
+    /// `base_fee_per_gas * gas_used`, destroyed by EIP-1559.
+    pub burnt_fee: BigInt,
+    /// `effective_priority_fee_per_gas * gas_used`, paid to the block producer.
+    pub miner_tip: BigInt,
+    /// `effective_gas_price * gas_used`, the sum of the burnt fee and the miner tip.
+    pub total_fee: BigInt,
+}
+
+impl Fees {
+    /// Blocks before London have no base fee, the whole gas price goes to the miner then.
+    pub fn new(tx: &TransactionTrace, base_fee_per_gas: &BigInt) -> Fees {
+        let gas_used = BigInt::from(tx.gas_used);
+        let effective_gas_price = effective_gas_price(tx, base_fee_per_gas);
+        let effective_priority_fee_per_gas = if effective_gas_price > *base_fee_per_gas {
+            effective_gas_price.clone() - base_fee_per_gas.clone()
+        } else {
+            BigInt::zero()
+        };
+        Fees {
+            burnt_fee: base_fee_per_gas.clone() * gas_used.clone(),
+            miner_tip: effective_priority_fee_per_gas.clone() * gas_used.clone(),
+            total_fee: effective_gas_price.clone() * gas_used,
+            effective_gas_price,
+            effective_priority_fee_per_gas,
+        }
+    }
+}
+
+/// Every transaction type from EIP-1559 on (dynamic fee, blob, set code) has a fee cap, for which
+/// Firehose fills `gas_price` with the cap instead of the price paid.
+fn effective_gas_price(tx: &TransactionTrace, base_fee_per_gas: &BigInt) -> BigInt {
+    let gas_price = to_big_int(tx.gas_price.clone());
+    let max_fee_per_gas = to_big_int(tx.max_fee_per_gas.clone());
+    if max_fee_per_gas == BigInt::zero() || *base_fee_per_gas == BigInt::zero() {
+        return gas_price;
+    }
+    let price = base_fee_per_gas.clone() + to_big_int(tx.max_priority_fee_per_gas.clone());
+    if price < max_fee_per_gas {
+        price
+    } else {
+        max_fee_per_gas
+    }
+}
+
+/// Nearest-rank percentile of sorted values, zero when there are none.
+pub fn percentile(sorted: &[BigInt], percent: usize) -> BigInt {
+    if sorted.is_empty() {
+        return BigInt::zero();
+    }
+    let rank = (percent * sorted.len() + 99) / 100;
+    sorted[rank.clamp(1, sorted.len()) - 1].clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(values: &[u64]) -> Vec<BigInt> {
+        values.iter().map(|v| BigInt::from(*v)).collect()
+    }
+
+    fn pb_big_int(value: u64) -> Option<substreams_ethereum::pb::eth::v2::BigInt> {
+        Some(substreams_ethereum::pb::eth::v2::BigInt {
+            bytes: value.to_be_bytes().to_vec(),
+        })
+    }
+
+    #[test]
+    fn test_fees_blob_transaction() {
+        // type 3, `gas_price` holds the fee cap
+        let tx = TransactionTrace {
+            r#type: 3,
+            gas_used: 21_000,
+            gas_price: pb_big_int(100),
+            max_fee_per_gas: pb_big_int(100),
+            max_priority_fee_per_gas: pb_big_int(2),
+            ..Default::default()
+        };
+        let fees = Fees::new(&tx, &BigInt::from(30));
+
+        assert_eq!(BigInt::from(32), fees.effective_gas_price);
+        assert_eq!(BigInt::from(2), fees.effective_priority_fee_per_gas);
+        assert_eq!(BigInt::from(42_000), fees.miner_tip);
+        assert_eq!(BigInt::from(630_000), fees.burnt_fee);
+        assert_eq!(BigInt::from(672_000), fees.total_fee);
+    }
+
+    #[test]
+    fn test_fees_legacy_transaction() {
+        let tx = TransactionTrace {
+            r#type: 0,
+            gas_used: 21_000,
+            gas_price: pb_big_int(50),
+            ..Default::default()
+        };
+        let fees = Fees::new(&tx, &BigInt::from(30));
+
+        assert_eq!(BigInt::from(50), fees.effective_gas_price);
+        assert_eq!(BigInt::from(20), fees.effective_priority_fee_per_gas);
+    }
+
+    #[test]
+    fn test_percentile_empty() {
+        assert_eq!(BigInt::zero(), percentile(&[], 50));
+    }
+
+    #[test]
+    fn test_percentile_nearest_rank() {
+        let sorted = values(&[15, 20, 35, 40, 50]);
+        assert_eq!(BigInt::from(15), percentile(&sorted, 0));
+        assert_eq!(BigInt::from(15), percentile(&sorted, 10));
+        assert_eq!(BigInt::from(20), percentile(&sorted, 30));
+        assert_eq!(BigInt::from(35), percentile(&sorted, 50));
+        assert_eq!(BigInt::from(50), percentile(&sorted, 90));
+        assert_eq!(BigInt::from(50), percentile(&sorted, 100));
+    }
+}
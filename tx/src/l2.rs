@@ -0,0 +1,100 @@
+use substreams::Hex;
+use substreams_ethereum::pb::eth::v2::Block;
+
+/// L1Block predeploy updated by the deposit transaction opening every OP Stack block.
+const OP_L1_BLOCK: &str = "4200000000000000000000000000000000000015";
+/// ArbOS address called by the internal transaction opening every Arbitrum block.
+const ARBOS: &str = "00000000000000000000000000000000000a4b05";
+
+/// `setL1BlockValues(uint64,uint64,uint256,bytes32,uint64,bytes32,uint256,uint256)` before Ecotone.
+const SET_L1_BLOCK_VALUES: [u8; 4] = [0x01, 0x5d, 0x8e, 0xb9];
+/// `setL1BlockValuesEcotone()` with tightly packed values.
+const SET_L1_BLOCK_VALUES_ECOTONE: [u8; 4] = [0x44, 0x0a, 0x5e, 0x20];
+/// `setL1BlockValuesIsthmus()`, same layout as Ecotone with extra trailing values.
+const SET_L1_BLOCK_VALUES_ISTHMUS: [u8; 4] = [0x09, 0x89, 0x99, 0xbe];
+/// `startBlock(uint256,uint64,uint64,uint64)`
+const START_BLOCK: [u8; 4] = [0x6b, 0xf6, 0xa4, 0x2d];
+
+/// L1 block number the L2 block was derived from, read from the system transaction opening the
+/// block. `None` for L1 chains.
+pub fn l1_block_number(block: &Block) -> Option<u64> {
+    let tx = block.transaction_traces.first()?;
+    l1_block_number_from_input(&Hex(&tx.to).to_string(), &tx.input)
+}
+
+fn l1_block_number_from_input(to: &str, input: &[u8]) -> Option<u64> {
+    if input.len() < 4 {
+        return None;
+    }
+    let (selector, _) = input.split_at(4);
+    // The number sits in the same bytes with the ABI encoding and the packed Ecotone encoding.
+    let offset = match to {
+        OP_L1_BLOCK
+            if selector == SET_L1_BLOCK_VALUES
+                || selector == SET_L1_BLOCK_VALUES_ECOTONE
+                || selector == SET_L1_BLOCK_VALUES_ISTHMUS =>
+        {
+            28
+        }
+        ARBOS if selector == START_BLOCK => 60,
+        _ => return None,
+    };
+    let bytes = input.get(offset..offset + 8)?;
+    Some(u64::from_be_bytes(bytes.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(value: u64) -> Vec<u8> {
+        let mut word = vec![0u8; 24];
+        word.extend_from_slice(&value.to_be_bytes());
+        word
+    }
+
+    #[test]
+    fn test_op_bedrock() {
+        let mut input = SET_L1_BLOCK_VALUES.to_vec();
+        input.extend(word(17_000_000));
+        input.extend(word(1_680_000_000));
+        assert_eq!(
+            Some(17_000_000),
+            l1_block_number_from_input(OP_L1_BLOCK, &input)
+        );
+    }
+
+    #[test]
+    fn test_op_ecotone() {
+        let mut input = SET_L1_BLOCK_VALUES_ECOTONE.to_vec();
+        input.extend_from_slice(&1368u32.to_be_bytes());
+        input.extend_from_slice(&810949u32.to_be_bytes());
+        input.extend_from_slice(&3u64.to_be_bytes());
+        input.extend_from_slice(&1_710_000_000u64.to_be_bytes());
+        input.extend_from_slice(&19_500_000u64.to_be_bytes());
+        input.extend(vec![0u8; 128]);
+        assert_eq!(
+            Some(19_500_000),
+            l1_block_number_from_input(OP_L1_BLOCK, &input)
+        );
+    }
+
+    #[test]
+    fn test_arbitrum_start_block() {
+        let mut input = START_BLOCK.to_vec();
+        input.extend(word(30_000_000_000));
+        input.extend(word(18_000_000));
+        input.extend(word(150_000_000));
+        input.extend(word(1));
+        assert_eq!(Some(18_000_000), l1_block_number_from_input(ARBOS, &input));
+    }
+
+    #[test]
+    fn test_l1_transaction() {
+        let mut input = START_BLOCK.to_vec();
+        input.extend(word(1));
+        input.extend(word(2));
+        assert_eq!(None, l1_block_number_from_input(OP_L1_BLOCK, &input));
+        assert_eq!(None, l1_block_number_from_input(ARBOS, &[]));
+    }
+}
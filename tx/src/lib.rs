@@ -1,22 +1,51 @@
-use std::collections::HashMap;
+mod contracts;
+mod fees;
+mod l2;
+mod params;
+mod revert;
+mod selectors;
+
+use std::collections::{HashMap, HashSet};
+
+use fees::{percentile, Fees};
+use params::Params;
 
 use substreams::{scalar::BigInt, Hex};
 use substreams_database_change::pb::database::{table_change::Operation, DatabaseChanges};
 use substreams_ethereum::pb::eth::{
     self,
-    v2::{balance_change::Reason, transaction_trace, TransactionTrace, TransactionTraceStatus},
+    v2::{
+        balance_change::Reason, transaction_trace, CallType, TransactionTrace,
+        TransactionTraceStatus,
+    },
 };
 
 substreams_ethereum::init!();
 
 #[substreams::handlers::map]
-fn db_out(block: eth::v2::Block) -> Result<DatabaseChanges, substreams::errors::Error> {
+fn db_out(
+    params: String,
+    block: eth::v2::Block,
+) -> Result<DatabaseChanges, substreams::errors::Error> {
+    let params = Params::parse(&params)?;
     let mut database_changes: DatabaseChanges = Default::default();
 
-    create_block_change(&mut database_changes, &block);
+    create_block_change(&mut database_changes, &block, &params.blockchain);
+    create_withdrawal_changes(&mut database_changes, &block);
+    create_balance_changes(&mut database_changes, &block);
 
     block.transaction_traces.iter().for_each(|tx| {
         create_transaction_change(&mut database_changes, tx, &block);
+        create_log_changes(&mut database_changes, tx, &block);
+        create_access_list_changes(&mut database_changes, tx, &block);
+        create_native_transfer_changes(&mut database_changes, tx, &block);
+        create_contract_changes(&mut database_changes, tx, &block);
+        if params.traces {
+            create_trace_changes(&mut database_changes, tx, &block);
+        }
+        if !params.storage_addresses.is_empty() {
+            create_storage_changes(&mut database_changes, tx, &block, &params.storage_addresses);
+        }
     });
 
     Ok(database_changes)
@@ -30,7 +59,6 @@ fn create_transaction_change(
     let block_number = block.number;
     let block_time = block.timestamp().clone();
     let block_hash = block.hash.clone();
-    // let base_fee = block.header.unwrap().base_fee_per_gas;
     let mut keys = HashMap::new();
     keys.insert("hash".into(), hex_string(tx.hash.clone()));
     keys.insert("block_number".into(), block_number.to_string());
@@ -64,14 +92,376 @@ fn create_transaction_change(
     change.change("index", (None, tx.index));
     change.change("from", (None, hex_string(tx.from.clone())));
     change.change("to", (None, hex_string(tx.to.clone())));
-    change.change("data", (None, hex_string(tx.return_data.clone())));
-    let r#type = transaction_trace::Type::from_i32(tx.r#type).unwrap();
-    change.change("type", (None, r#type.as_str_name()));
-    // change.change("access_list", (None, tx.access_list.trace_address.clone().join(",")));
-    // change.change("priority_fee_per_gas", (None, ))
+    change.change("input", (None, hex_string(tx.input.clone())));
+    change.change("return_data", (None, hex_string(tx.return_data.clone())));
+    let method_id = selectors::method_id(&tx.input);
+    change.change("method_id", (None, hex_string(method_id.to_vec())));
+    change.change(
+        "method_signature",
+        (
+            None,
+            selectors::method_signature(method_id).unwrap_or_default(),
+        ),
+    );
+    change.change("type", (None, transaction_type_name(tx.r#type)));
+    let root_call = tx.calls.first();
+    change.change(
+        "failure_reason",
+        (
+            None,
+            root_call.map_or(String::new(), |call| call.failure_reason.clone()),
+        ),
+    );
+    let revert_data: &[u8] = if status == TransactionTraceStatus::Succeeded {
+        &[][..]
+    } else {
+        root_call.map_or(&tx.return_data, |call| &call.return_data)
+    };
+    change.change("revert_reason", (None, revert::revert_reason(revert_data)));
+    change.change(
+        "revert_selector",
+        (
+            None,
+            hex_string(revert::revert_selector(revert_data).to_vec()),
+        ),
+    );
+    change.change(
+        "max_fee_per_blob_gas",
+        (None, big_int_to_string(tx.blob_gas_fee_cap.clone())),
+    );
+    change.change(
+        "blob_versioned_hashes",
+        (None, hex_array_string(&tx.blob_hashes)),
+    );
+
+    let receipt = tx.receipt.clone().unwrap_or_default();
+    change.change("cumulative_gas_used", (None, receipt.cumulative_gas_used));
+    change.change("logs_bloom", (None, hex_string(receipt.logs_bloom)));
+    change.change("blob_gas_used", (None, receipt.blob_gas_used.unwrap_or(0)));
+    change.change(
+        "blob_gas_price",
+        (None, big_int_to_string(receipt.blob_gas_price)),
+    );
+    change.change(
+        "created_contract_address",
+        (None, hex_string(created_contract_address(tx))),
+    );
+    let fees = Fees::new(tx, &base_fee_per_gas(block));
+    change.change(
+        "effective_gas_price",
+        (None, fees.effective_gas_price.to_string()),
+    );
+    change.change(
+        "effective_priority_fee_per_gas",
+        (None, fees.effective_priority_fee_per_gas.to_string()),
+    );
+    change.change("burnt_fee", (None, fees.burnt_fee.to_string()));
+    change.change("miner_tip", (None, fees.miner_tip.to_string()));
+    change.change("total_fee", (None, fees.total_fee.to_string()));
+}
+
+fn create_log_changes(
+    database_changes: &mut DatabaseChanges,
+    tx: &TransactionTrace,
+    block: &eth::v2::Block,
+) {
+    let receipt = match tx.receipt.as_ref() {
+        Some(receipt) => receipt,
+        None => return,
+    };
+    let block_time = block.timestamp().clone();
+    for log in receipt.logs.iter() {
+        let mut keys = HashMap::new();
+        keys.insert("tx_hash".into(), hex_string(tx.hash.clone()));
+        keys.insert("log_index".into(), log.block_index.to_string());
+        keys.insert("block_number".into(), block.number.to_string());
+        keys.insert("block_time".into(), block_time.to_string());
+        let change =
+            database_changes.push_change_composite("logs", keys, log.ordinal, Operation::Create);
+        change.change("block_hash", (None, hex_string(block.hash.clone())));
+        change.change("tx_index", (None, tx.index));
+        change.change("tx_log_index", (None, log.index));
+        change.change("address", (None, hex_string(log.address.clone())));
+        change.change("topics", (None, hex_array_string(&log.topics)));
+        change.change("data", (None, hex_string(log.data.clone())));
+    }
+}
+
+fn create_access_list_changes(
+    database_changes: &mut DatabaseChanges,
+    tx: &TransactionTrace,
+    block: &eth::v2::Block,
+) {
+    let block_time = block.timestamp().clone();
+    for (index, access) in tx.access_list.iter().enumerate() {
+        let mut keys = HashMap::new();
+        keys.insert("tx_hash".into(), hex_string(tx.hash.clone()));
+        keys.insert("access_index".into(), index.to_string());
+        keys.insert("block_number".into(), block.number.to_string());
+        keys.insert("block_time".into(), block_time.to_string());
+        let change = database_changes.push_change_composite(
+            "access_list",
+            keys,
+            tx.begin_ordinal,
+            Operation::Create,
+        );
+        change.change("address", (None, hex_string(access.address.clone())));
+        change.change(
+            "storage_keys",
+            (None, hex_array_string(&access.storage_keys)),
+        );
+    }
+}
+
+fn create_trace_changes(
+    database_changes: &mut DatabaseChanges,
+    tx: &TransactionTrace,
+    block: &eth::v2::Block,
+) {
+    let block_time = block.timestamp().clone();
+    for call in tx.calls.iter() {
+        let mut keys = HashMap::new();
+        keys.insert("tx_hash".into(), hex_string(tx.hash.clone()));
+        keys.insert("call_index".into(), call.index.to_string());
+        keys.insert("block_number".into(), block.number.to_string());
+        keys.insert("block_time".into(), block_time.to_string());
+        let change = database_changes.push_change_composite(
+            "traces",
+            keys,
+            call.begin_ordinal,
+            Operation::Create,
+        );
+        let call_type = CallType::from_i32(call.call_type).unwrap_or(CallType::Unspecified);
+        change.change("block_hash", (None, hex_string(block.hash.clone())));
+        change.change("tx_index", (None, tx.index));
+        change.change("parent_index", (None, call.parent_index));
+        change.change("depth", (None, call.depth));
+        change.change("call_type", (None, call_type.as_str_name()));
+        change.change("caller", (None, hex_string(call.caller.clone())));
+        change.change("address", (None, hex_string(call.address.clone())));
+        change.change("value", (None, big_int_to_string(call.value.clone())));
+        change.change("gas_limit", (None, call.gas_limit));
+        change.change("gas_consumed", (None, call.gas_consumed));
+        change.change("input", (None, hex_string(call.input.clone())));
+        change.change("output", (None, hex_string(call.return_data.clone())));
+        change.change("status_failed", (None, call.status_failed));
+        change.change("status_reverted", (None, call.status_reverted));
+        change.change("state_reverted", (None, call.state_reverted));
+        change.change("failure_reason", (None, call.failure_reason.clone()));
+    }
+}
+
+fn create_balance_changes(database_changes: &mut DatabaseChanges, block: &eth::v2::Block) {
+    let block_time = block.timestamp().clone();
+    let block_changes = block.balance_changes.iter().map(|bc| (None, bc));
+    let call_changes = block.transaction_traces.iter().flat_map(|tx| {
+        tx.calls.iter().flat_map(move |call| {
+            call.balance_changes
+                .iter()
+                .filter(move |bc| !call.state_reverted || is_gas_balance_change(bc))
+                .map(move |bc| (Some(tx), bc))
+        })
+    });
+    for (tx, balance_change) in block_changes.chain(call_changes) {
+        let mut keys = HashMap::new();
+        keys.insert("block_time".into(), block_time.to_string());
+        keys.insert("block_number".into(), block.number.to_string());
+        keys.insert("ordinal".into(), balance_change.ordinal.to_string());
+        let change = database_changes.push_change_composite(
+            "balance_changes",
+            keys,
+            balance_change.ordinal,
+            Operation::Create,
+        );
+        let reason = Reason::from_i32(balance_change.reason).unwrap_or(Reason::Unknown);
+        change.change("block_hash", (None, hex_string(block.hash.clone())));
+        change.change(
+            "tx_hash",
+            (
+                None,
+                tx.map_or(String::new(), |tx| hex_string(tx.hash.clone())),
+            ),
+        );
+        change.change(
+            "address",
+            (None, hex_string(balance_change.address.clone())),
+        );
+        change.change(
+            "old_value",
+            (None, big_int_to_string(balance_change.old_value.clone())),
+        );
+        change.change(
+            "new_value",
+            (None, big_int_to_string(balance_change.new_value.clone())),
+        );
+        change.change("reason", (None, reason.as_str_name()));
+    }
+}
+
+/// Value transfers between accounts, including the internal ones made by contracts.
+///
+/// Calls with `state_reverted` set had their state changes rolled back, so the value never moved.
+/// For failed and reverted transactions every call is marked that way.
+fn create_native_transfer_changes(
+    database_changes: &mut DatabaseChanges,
+    tx: &TransactionTrace,
+    block: &eth::v2::Block,
+) {
+    let block_time = block.timestamp().clone();
+    for call in tx.calls.iter().filter(|call| {
+        !call.state_reverted
+            && call.call_type != CallType::Delegate as i32
+            && call.call_type != CallType::Static as i32
+            && call.call_type != CallType::Callcode as i32
+    }) {
+        let value = to_big_int(call.value.clone());
+        if value <= BigInt::zero() {
+            continue;
+        }
+        let mut keys = HashMap::new();
+        keys.insert("tx_hash".into(), hex_string(tx.hash.clone()));
+        keys.insert("call_index".into(), call.index.to_string());
+        keys.insert("block_number".into(), block.number.to_string());
+        keys.insert("block_time".into(), block_time.to_string());
+        let change = database_changes.push_change_composite(
+            "native_transfers",
+            keys,
+            call.begin_ordinal,
+            Operation::Create,
+        );
+        let call_type = CallType::from_i32(call.call_type).unwrap_or(CallType::Unspecified);
+        change.change("block_hash", (None, hex_string(block.hash.clone())));
+        change.change("tx_index", (None, tx.index));
+        change.change("depth", (None, call.depth));
+        change.change("call_type", (None, call_type.as_str_name()));
+        change.change("from", (None, hex_string(call.caller.clone())));
+        change.change("to", (None, hex_string(call.address.clone())));
+        change.change("value", (None, value.to_string()));
+    }
 }
 
-fn create_block_change(database_changes: &mut DatabaseChanges, block: &eth::v2::Block) {
+/// Contracts deployed by the transaction and the ones it self-destructed, one row each.
+fn create_contract_changes(
+    database_changes: &mut DatabaseChanges,
+    tx: &TransactionTrace,
+    block: &eth::v2::Block,
+) {
+    let block_time = block.timestamp().clone();
+    for call in tx.calls.iter().filter(|call| !call.state_reverted) {
+        let kind = if call.call_type == CallType::Create as i32 {
+            contracts::creation_kind(tx, call)
+        } else if call.suicide {
+            "SELFDESTRUCT"
+        } else {
+            continue;
+        };
+        let ordinal = call
+            .account_creations
+            .iter()
+            .find(|creation| creation.account == call.address)
+            .map_or(call.begin_ordinal, |creation| creation.ordinal);
+        let code = call
+            .code_changes
+            .iter()
+            .find(|code_change| code_change.address == call.address);
+
+        let mut keys = HashMap::new();
+        keys.insert("address".into(), hex_string(call.address.clone()));
+        keys.insert("block_number".into(), block.number.to_string());
+        keys.insert("ordinal".into(), ordinal.to_string());
+        let change =
+            database_changes.push_change_composite("contracts", keys, ordinal, Operation::Create);
+        change.change("block_time", (None, block_time.to_string()));
+        change.change("block_hash", (None, hex_string(block.hash.clone())));
+        change.change("tx_hash", (None, hex_string(tx.hash.clone())));
+        change.change("tx_index", (None, tx.index));
+        change.change("tx_from", (None, hex_string(tx.from.clone())));
+        change.change("deployer", (None, hex_string(call.caller.clone())));
+        change.change("kind", (None, kind));
+        change.change(
+            "code_hash",
+            (
+                None,
+                code.map_or(String::new(), |code| hex_string(code.new_hash.clone())),
+            ),
+        );
+        change.change(
+            "bytecode_size",
+            (None, code.map_or(0, |code| code.new_code.len() as u64)),
+        );
+    }
+}
+
+/// Gas is bought and refunded even when the call reverts, so these changes are never rolled back.
+fn is_gas_balance_change(balance_change: &eth::v2::BalanceChange) -> bool {
+    balance_change.reason == Reason::GasBuy as i32
+        || balance_change.reason == Reason::GasRefund as i32
+        || balance_change.reason == Reason::RewardTransactionFee as i32
+}
+
+/// Storage writes to the given contracts, writes of reverted calls were rolled back and are skipped.
+fn create_storage_changes(
+    database_changes: &mut DatabaseChanges,
+    tx: &TransactionTrace,
+    block: &eth::v2::Block,
+    addresses: &Vec<Vec<u8>>,
+) {
+    let block_time = block.timestamp().clone();
+    for call in tx.calls.iter().filter(|call| !call.state_reverted) {
+        for storage_change in call
+            .storage_changes
+            .iter()
+            .filter(|storage_change| addresses.contains(&storage_change.address))
+        {
+            let mut keys = HashMap::new();
+            keys.insert("tx_hash".into(), hex_string(tx.hash.clone()));
+            keys.insert("address".into(), hex_string(storage_change.address.clone()));
+            keys.insert("slot".into(), hex_string(storage_change.key.clone()));
+            keys.insert("ordinal".into(), storage_change.ordinal.to_string());
+            let change = database_changes.push_change_composite(
+                "storage_changes",
+                keys,
+                storage_change.ordinal,
+                Operation::Create,
+            );
+            change.change("block_number", (None, block.number));
+            change.change("block_time", (None, block_time.to_string()));
+            change.change("block_hash", (None, hex_string(block.hash.clone())));
+            change.change("tx_index", (None, tx.index));
+            change.change("call_index", (None, call.index));
+            change.change(
+                "old_value",
+                (None, hex_string(storage_change.old_value.clone())),
+            );
+            change.change(
+                "new_value",
+                (None, hex_string(storage_change.new_value.clone())),
+            );
+        }
+    }
+}
+
+/// Name of the transaction type, types unknown to the firehose protobuf keep their number.
+fn transaction_type_name(r#type: i32) -> String {
+    match transaction_trace::Type::from_i32(r#type) {
+        Some(r#type) => r#type.as_str_name().to_string(),
+        None => format!("TRX_TYPE_{}", r#type),
+    }
+}
+
+/// Address of the contract created by the transaction, empty when it is not a deployment.
+fn created_contract_address(tx: &TransactionTrace) -> Vec<u8> {
+    tx.calls
+        .first()
+        .filter(|call| call.call_type == CallType::Create as i32)
+        .map_or(vec![], |call| call.address.clone())
+}
+
+fn create_block_change(
+    database_changes: &mut DatabaseChanges,
+    block: &eth::v2::Block,
+    blockchain: &str,
+) {
     let block_number = block.number;
     let header = block.header.as_ref();
     let block_time = block.timestamp();
@@ -83,19 +473,11 @@ fn create_block_change(database_changes: &mut DatabaseChanges, block: &eth::v2::
         block_keys.insert("hash".into(), Hex(block.hash.clone()).to_string());
         let block_change =
             database_changes.push_change_composite("blocks", block_keys, 0, Operation::Create);
+        block_change.change("blockchain", (None, blockchain));
         block_change.change("parent_hash", (None, hex_string(header.parent_hash)));
         block_change.change("gas_limit", (None, header.gas_limit));
         block_change.change("gas_used", (None, header.gas_used));
-        let miner = block
-            .balance_changes
-            .iter()
-            .find(|bc| bc.reason == Reason::RewardMineBlock as i32)
-            .map_or(
-                "0000000000000000000000000000000000000000".to_string(),
-                |m| hex_string(m.address.clone()),
-            );
-
-        block_change.change("miner", (None, miner));
+        block_change.change("miner", (None, hex_string(fee_recipient(block))));
         block_change.change(
             "difficulty",
             (
@@ -114,15 +496,174 @@ fn create_block_change(database_changes: &mut DatabaseChanges, block: &eth::v2::
             "base_fee_per_gas",
             (None, big_int_to_string(header.base_fee_per_gas)),
         );
+
+        let base_fee_per_gas = base_fee_per_gas(block);
+        let fees: Vec<Fees> = block
+            .transaction_traces
+            .iter()
+            .map(|tx| Fees::new(tx, &base_fee_per_gas))
+            .collect();
+        let mut gas_prices: Vec<BigInt> = fees
+            .iter()
+            .map(|tx_fees| tx_fees.effective_gas_price.clone())
+            .collect();
+        gas_prices.sort();
+        let senders: HashSet<&Vec<u8>> =
+            block.transaction_traces.iter().map(|tx| &tx.from).collect();
+        block_change.change("tx_count", (None, block.transaction_traces.len() as u64));
+        block_change.change("unique_senders", (None, senders.len() as u64));
+        block_change.change(
+            "total_burnt_fee",
+            (
+                None,
+                fees.iter()
+                    .fold(BigInt::zero(), |total, tx_fees| {
+                        total + tx_fees.burnt_fee.clone()
+                    })
+                    .to_string(),
+            ),
+        );
+        block_change.change(
+            "total_tips",
+            (
+                None,
+                fees.iter()
+                    .fold(BigInt::zero(), |total, tx_fees| {
+                        total + tx_fees.miner_tip.clone()
+                    })
+                    .to_string(),
+            ),
+        );
+        for percent in [10, 25, 50, 75, 90] {
+            block_change.change(
+                format!("gas_price_p{}", percent).as_str(),
+                (None, percentile(&gas_prices, percent).to_string()),
+            );
+        }
+        block_change.change(
+            "withdrawals_root",
+            (None, hex_string(header.withdrawals_root)),
+        );
+        block_change.change("blob_gas_used", (None, header.blob_gas_used.unwrap_or(0)));
+        block_change.change(
+            "excess_blob_gas",
+            (None, header.excess_blob_gas.unwrap_or(0)),
+        );
+        block_change.change(
+            "l1_block_number",
+            (None, l2::l1_block_number(block).unwrap_or(0)),
+        );
+        let withdrawals = block
+            .balance_changes
+            .iter()
+            .filter(|bc| bc.reason == Reason::Withdrawal as i32);
+        block_change.change(
+            "withdrawal_count",
+            (None, withdrawals.clone().count() as u64),
+        );
+        block_change.change(
+            "withdrawal_amount",
+            (
+                None,
+                withdrawals
+                    .map(|bc| balance_change_amount(bc))
+                    .fold(BigInt::zero(), |total, amount| total + amount)
+                    .to_string(),
+            ),
+        );
+    }
+}
+
+/// Address receiving the fees of the block.
+///
+/// The header coinbase covers proof-of-stake Ethereum and the L2s. When it is empty, the
+/// proof-of-work block reward is used, then the transaction fee rewards, which Bor credits to the
+/// block producer while leaving the coinbase empty.
+fn fee_recipient(block: &eth::v2::Block) -> Vec<u8> {
+    let coinbase = block
+        .header
+        .as_ref()
+        .map(|header| header.coinbase.clone())
+        .filter(|coinbase| coinbase.iter().any(|b| *b != 0));
+    if let Some(coinbase) = coinbase {
+        return coinbase;
+    }
+    let block_reward = block
+        .balance_changes
+        .iter()
+        .find(|bc| bc.reason == Reason::RewardMineBlock as i32);
+    let transaction_fee = block
+        .transaction_traces
+        .iter()
+        .flat_map(|tx| tx.calls.iter())
+        .flat_map(|call| call.balance_changes.iter())
+        .find(|bc| bc.reason == Reason::RewardTransactionFee as i32);
+    block_reward
+        .or(transaction_fee)
+        .map_or(vec![0; 20], |bc| bc.address.clone())
+}
+
+fn create_withdrawal_changes(database_changes: &mut DatabaseChanges, block: &eth::v2::Block) {
+    let block_time = block.timestamp().clone();
+    for withdrawal in block
+        .balance_changes
+        .iter()
+        .filter(|bc| bc.reason == Reason::Withdrawal as i32)
+    {
+        let mut keys = HashMap::new();
+        keys.insert("block_time".into(), block_time.to_string());
+        keys.insert("block_number".into(), block.number.to_string());
+        keys.insert("ordinal".into(), withdrawal.ordinal.to_string());
+        let change = database_changes.push_change_composite(
+            "withdrawals",
+            keys,
+            withdrawal.ordinal,
+            Operation::Create,
+        );
+        change.change("block_hash", (None, hex_string(block.hash.clone())));
+        change.change("address", (None, hex_string(withdrawal.address.clone())));
+        change.change(
+            "amount",
+            (None, balance_change_amount(withdrawal).to_string()),
+        );
     }
 }
 
+fn balance_change_amount(balance_change: &eth::v2::BalanceChange) -> BigInt {
+    to_big_int(balance_change.new_value.clone()) - to_big_int(balance_change.old_value.clone())
+}
+
+fn base_fee_per_gas(block: &eth::v2::Block) -> BigInt {
+    to_big_int(
+        block
+            .header
+            .as_ref()
+            .and_then(|header| header.base_fee_per_gas.clone()),
+    )
+}
+
 fn big_int_to_string(big_int: Option<eth::v2::BigInt>) -> String {
-    big_int.map_or("0".to_string(), |f| {
-        BigInt::from_unsigned_bytes_be(f.bytes.as_slice()).to_string()
+    to_big_int(big_int).to_string()
+}
+
+fn to_big_int(big_int: Option<eth::v2::BigInt>) -> BigInt {
+    big_int.map_or(BigInt::zero(), |f| {
+        BigInt::from_unsigned_bytes_be(f.bytes.as_slice())
     })
 }
 
 fn hex_string(hash: Vec<u8>) -> String {
     Hex(hash).to_string()
 }
+
+// ClickHouse array literal, the items being quoted strings
+fn hex_array_string(values: &Vec<Vec<u8>>) -> String {
+    format!(
+        "[{}]",
+        values
+            .iter()
+            .map(|value| format!("'{}'", Hex(value)))
+            .collect::<Vec<_>>()
+            .join(",")
+    )
+}
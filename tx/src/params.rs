@@ -0,0 +1,115 @@
+/// Module params of `db_out`, given as `key=value` pairs separated by `&`, e.g.
+/// `blockchain=ethereum&traces=true`.
+#[derive(Debug, Default, PartialEq)]
+pub struct Params {
+    /// Chain name written to the `blockchain` column of the `blocks` table.
+    pub blockchain: String,
+    /// Write the call traces of every transaction to the `traces` table.
+    pub traces: bool,
+    /// Write the storage changes of these contracts to the `storage_changes` table, given as a
+    /// comma separated list of hex addresses.
+    pub storage_addresses: Vec<Vec<u8>>,
+}
+
+impl Params {
+    pub fn parse(params: &str) -> Result<Params, substreams::errors::Error> {
+        let mut result = Params::default();
+        for pair in params.split('&').map(str::trim).filter(|p| !p.is_empty()) {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("invalid param `{}`, expected key=value", pair))?;
+            match key.trim() {
+                "blockchain" => result.blockchain = value.trim().to_string(),
+                "traces" => result.traces = parse_bool(key, value)?,
+                "storage_addresses" => result.storage_addresses = parse_addresses(key, value)?,
+                _ => return Err(anyhow::anyhow!("unknown param `{}`", key)),
+            }
+        }
+        if result.blockchain.is_empty() {
+            return Err(anyhow::anyhow!("param `blockchain` is required"));
+        }
+        Ok(result)
+    }
+}
+
+fn parse_bool(key: &str, value: &str) -> Result<bool, substreams::errors::Error> {
+    value
+        .trim()
+        .parse::<bool>()
+        .map_err(|_| anyhow::anyhow!("param `{}` must be true or false, got `{}`", key, value))
+}
+
+fn parse_addresses(key: &str, value: &str) -> Result<Vec<Vec<u8>>, substreams::errors::Error> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|address| !address.is_empty())
+        .map(|address| {
+            hex::decode(address.trim_start_matches("0x"))
+                .ok()
+                .filter(|bytes| bytes.len() == 20)
+                .ok_or_else(|| {
+                    anyhow::anyhow!("param `{}` has an invalid address `{}`", key, address)
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_blockchain_params() {
+        assert_eq!(
+            Params {
+                blockchain: "polygon".to_string(),
+                ..Default::default()
+            },
+            Params::parse("blockchain=polygon").unwrap()
+        );
+        assert!(Params::parse("").is_err());
+        assert!(Params::parse("traces=true").is_err());
+    }
+
+    #[test]
+    fn test_parse_traces() {
+        assert!(
+            Params::parse("blockchain=ethereum&traces=true")
+                .unwrap()
+                .traces
+        );
+        assert!(
+            !Params::parse("blockchain=ethereum&traces=false")
+                .unwrap()
+                .traces
+        );
+    }
+
+    #[test]
+    fn test_parse_storage_addresses() {
+        let params = Params::parse(
+            "blockchain=ethereum&traces=false&storage_addresses=0x1F98431c8aD98523631AE4a59f267346ea31F984, c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2",
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                hex::decode("1f98431c8ad98523631ae4a59f267346ea31f984").unwrap(),
+                hex::decode("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2").unwrap(),
+            ],
+            params.storage_addresses
+        );
+        assert!(Params::parse("blockchain=ethereum&storage_addresses=")
+            .unwrap()
+            .storage_addresses
+            .is_empty());
+    }
+
+    #[test]
+    fn test_parse_invalid_params() {
+        assert!(Params::parse("blockchain=ethereum&traces").is_err());
+        assert!(Params::parse("blockchain=ethereum&traces=yes").is_err());
+        assert!(Params::parse("blockchain=ethereum&unknown=true").is_err());
+        assert!(Params::parse("blockchain=ethereum&storage_addresses=0x1234").is_err());
+    }
+}
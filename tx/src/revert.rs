@@ -0,0 +1,90 @@
+use ethabi::ParamType;
+
+/// `Error(string)`
+const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+/// `Panic(uint256)`
+const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+/// Message of a revert payload, `Error(string)` and `Panic(uint256)` are the ones Solidity emits
+/// itself, anything else is a custom error only known through its selector.
+pub fn revert_reason(return_data: &[u8]) -> String {
+    if return_data.len() < 4 {
+        return String::new();
+    }
+    let (selector, data) = return_data.split_at(4);
+    if selector == ERROR_SELECTOR {
+        return ethabi::decode(&[ParamType::String], data)
+            .ok()
+            .and_then(|mut tokens| tokens.pop())
+            .and_then(|token| token.into_string())
+            .unwrap_or_default();
+    }
+    if selector == PANIC_SELECTOR && data.len() >= 32 && data[..24].iter().all(|b| *b == 0) {
+        let code = u64::from_be_bytes(data[24..32].try_into().unwrap());
+        return format!("Panic(0x{:02x}): {}", code, panic_meaning(code));
+    }
+    String::new()
+}
+
+/// 4-byte selector of the revert payload, empty when the call reverted without data.
+pub fn revert_selector(return_data: &[u8]) -> &[u8] {
+    if return_data.len() < 4 {
+        return &[];
+    }
+    &return_data[0..4]
+}
+
+/// Panic codes from https://docs.soliditylang.org/en/latest/control-structures.html#panic-via-assert-and-error-via-require
+fn panic_meaning(code: u64) -> &'static str {
+    match code {
+        0x00 => "generic compiler panic",
+        0x01 => "assertion failed",
+        0x11 => "arithmetic overflow or underflow",
+        0x12 => "division or modulo by zero",
+        0x21 => "invalid enum value",
+        0x22 => "invalid storage byte array encoding",
+        0x31 => "pop on empty array",
+        0x32 => "array index out of bounds",
+        0x41 => "out of memory",
+        0x51 => "call to zero-initialized function",
+        _ => "unknown panic code",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_string() {
+        let mut data = ERROR_SELECTOR.to_vec();
+        data.extend(ethabi::encode(&[ethabi::Token::String(
+            "ERC20: transfer amount exceeds balance".to_string(),
+        )]));
+        assert_eq!(
+            "ERC20: transfer amount exceeds balance",
+            revert_reason(&data)
+        );
+        assert_eq!(&ERROR_SELECTOR, revert_selector(&data));
+    }
+
+    #[test]
+    fn test_panic() {
+        let mut data = PANIC_SELECTOR.to_vec();
+        data.extend([0u8; 31]);
+        data.push(0x11);
+        assert_eq!(
+            "Panic(0x11): arithmetic overflow or underflow",
+            revert_reason(&data)
+        );
+    }
+
+    #[test]
+    fn test_custom_error() {
+        // InsufficientLiquidity()
+        let data = [0xbb, 0x55, 0xfd, 0x27];
+        assert_eq!("", revert_reason(&data));
+        assert_eq!(&data, revert_selector(&data));
+        assert!(revert_selector(&[]).is_empty());
+    }
+}
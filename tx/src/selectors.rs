@@ -0,0 +1,43 @@
+// sorted by selector, generated by build.rs from selectors.tsv
+include!(concat!(env!("OUT_DIR"), "/selectors.rs"));
+
+/// 4-byte selector of the called method, empty for plain transfers.
+pub fn method_id(input: &[u8]) -> &[u8] {
+    if input.len() < 4 {
+        return &[];
+    }
+    &input[0..4]
+}
+
+/// Signature of the called method when its selector is in the embedded dictionary.
+pub fn method_signature(method_id: &[u8]) -> Option<&'static str> {
+    let selector = u32::from_be_bytes(method_id.try_into().ok()?);
+    SELECTORS
+        .binary_search_by_key(&selector, |(selector, _)| *selector)
+        .ok()
+        .map(|index| SELECTORS[index].1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_method_id() {
+        assert_eq!(
+            &[0xa9, 0x05, 0x9c, 0xbb],
+            method_id(&[0xa9, 0x05, 0x9c, 0xbb, 0x00, 0x01])
+        );
+        assert!(method_id(&[0xa9, 0x05]).is_empty());
+    }
+
+    #[test]
+    fn test_method_signature() {
+        assert_eq!(
+            Some("transfer(address,uint256)"),
+            method_signature(&[0xa9, 0x05, 0x9c, 0xbb])
+        );
+        assert_eq!(None, method_signature(&[0xff, 0xff, 0xff, 0xff]));
+        assert_eq!(None, method_signature(&[]));
+    }
+}